@@ -1,21 +1,163 @@
-use hello_world::greeter_client::GreeterClient;
-use hello_world::HelloRequest;
+mod output;
 
-pub mod hello_world {
-    tonic::include_proto!("helloworld");
+use crate::output::{print, print_change, Fuel, Format, PriceChange, Station};
+
+use refuel::fuel_prices_client::FuelPricesClient;
+use refuel::{GetCurrentPricesRequest, GetPriceHistoryRequest, ListStationsRequest, WatchPriceChangesRequest};
+
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use tonic::transport::Channel;
+
+pub mod refuel {
+    tonic::include_proto!("refuel");
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut client = GreeterClient::connect("http://[::1]:50051").await?;
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+#[command(propagate_version = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+    #[arg(short, long, value_name = "URL", default_value = "http://[::1]:50051", global = true)]
+    /// Address of the refuel gRPC server
+    server: String,
+    #[arg(short, long, value_enum, default_value_t = Format::Table, global = true)]
+    /// Output format
+    format: Format,
+    #[arg(long, value_name = "FUEL", value_enum, value_delimiter = ',', global = true)]
+    /// Only show prices of these fuel types, all if not given
+    fuel: Vec<Fuel>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// List all known refuel stations
+    Stations,
+    /// Show the current price of every refuel station
+    Prices,
+    /// Show the price history of one refuel station
+    History {
+        /// Id or name of the refuel station
+        station: String,
+        #[arg(short, long)]
+        /// Address of the refuel station, required if the name is ambiguous
+        addr: Option<String>,
+        #[arg(long, value_name = "RFC3339")]
+        /// Only show price changes since this time
+        from: Option<DateTime<Utc>>,
+        #[arg(long, value_name = "RFC3339")]
+        /// Only show price changes until this time
+        to: Option<DateTime<Utc>>,
+    },
+    /// Show the refuel stations with the lowest current price of each fuel type and currency
+    Cheapest {
+        #[arg(short = 'n', long, default_value_t = 1)]
+        /// Number of refuel stations to show per fuel type and currency
+        count: usize,
+    },
+    /// Print price changes as they are scraped
+    Watch {
+        #[arg(long)]
+        /// Only watch refuel stations whose name contains this text
+        name: Option<String>,
+        #[arg(short, long)]
+        /// Only watch refuel stations whose address contains this text
+        addr: Option<String>,
+    },
+}
 
-    let request = tonic::Request::new(HelloRequest {
-        name: "Tonic".into(),
-    });
+fn to_timestamp(datetime: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: datetime.timestamp(),
+        nanos: datetime.timestamp_subsec_nanos() as i32,
+    }
+}
+
+async fn find_station(client: &mut FuelPricesClient<Channel>, name: &str, addr: Option<&str>) -> Result<refuel::Station, Box<dyn std::error::Error>> {
+    if let Ok(id) = name.parse() {
+        return Ok(refuel::Station { id, ..Default::default() });
+    }
+
+    let stations = client.list_stations(ListStationsRequest {}).await?.into_inner().stations;
+    let mut matches: Vec<refuel::Station> = stations.into_iter()
+        .filter(|station| station.name == name && addr.is_none_or(|addr| station.addr == addr))
+        .collect();
+    match matches.len() {
+        0 => Err(format!("refuel station {name:?} not found").into()),
+        1 => Ok(matches.remove(0)),
+        _ => Err(format!("refuel station {name:?} is ambiguous, use --addr").into()),
+    }
+}
+
+async fn cmd_stations(client: &mut FuelPricesClient<Channel>, format: Format) -> Result<(), Box<dyn std::error::Error>> {
+    let stations = client.list_stations(ListStationsRequest {}).await?.into_inner().stations;
+    let stations: Vec<Station> = stations.into_iter().map(Into::into).collect();
+    print(format, &stations)?;
+    Ok(())
+}
 
-    let response = client.say_hello(request).await?;
+async fn cmd_prices(client: &mut FuelPricesClient<Channel>, format: Format, fuels: Vec<i32>) -> Result<(), Box<dyn std::error::Error>> {
+    let prices = client.get_current_prices(GetCurrentPricesRequest { fuels }).await?.into_inner().prices;
+    let prices: Vec<PriceChange> = prices.into_iter().map(Into::into).collect();
+    print(format, &prices)?;
+    Ok(())
+}
+
+async fn cmd_history(client: &mut FuelPricesClient<Channel>, format: Format, fuels: Vec<i32>, name: &str, addr: Option<&str>,
+                     from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Result<(), Box<dyn std::error::Error>> {
+    let station = find_station(client, name, addr).await?;
+    let request = GetPriceHistoryRequest {
+        station: Some(station),
+        from: from.map(to_timestamp),
+        to: to.map(to_timestamp),
+        fuels,
+    };
+    let prices = client.get_price_history(request).await?.into_inner().prices;
+    let prices: Vec<PriceChange> = prices.into_iter().map(Into::into).collect();
+    print(format, &prices)?;
+    Ok(())
+}
+
+async fn cmd_cheapest(client: &mut FuelPricesClient<Channel>, format: Format, fuels: Vec<i32>, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut prices = client.get_current_prices(GetCurrentPricesRequest { fuels }).await?.into_inner().prices;
+    // prices in different currencies are not comparable
+    prices.sort_by(|a, b| (a.fuel, &a.currency, a.price).cmp(&(b.fuel, &b.currency, b.price)));
+    let mut cheapest: Vec<refuel::PriceChange> = Vec::new();
+    for change in prices {
+        let shown = cheapest.iter().filter(|cheap| cheap.fuel == change.fuel && cheap.currency == change.currency).count();
+        if shown < count {
+            cheapest.push(change);
+        }
+    }
+    let prices: Vec<PriceChange> = cheapest.into_iter().map(Into::into).collect();
+    print(format, &prices)?;
+    Ok(())
+}
+
+async fn cmd_watch(client: &mut FuelPricesClient<Channel>, format: Format, fuels: Vec<i32>, name: Option<String>, addr: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let request = WatchPriceChangesRequest { name, addr, fuels };
+    let mut stream = client.watch_price_changes(request).await?.into_inner();
+    while let Some(change) = stream.message().await? {
+        print_change(format, &change.into())?;
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let mut client = FuelPricesClient::connect(cli.server).await?;
+    let format = cli.format;
+    let fuels: Vec<i32> = cli.fuel.into_iter().map(|fuel| refuel::FuelType::from(fuel).into()).collect();
 
-    println!("RESPONSE={:?}", response);
+    match cli.command {
+        Commands::Stations => { cmd_stations(&mut client, format).await? }
+        Commands::Prices => { cmd_prices(&mut client, format, fuels).await? }
+        Commands::History { station, addr, from, to } => { cmd_history(&mut client, format, fuels, &station, addr.as_deref(), from, to).await? }
+        Commands::Cheapest { count } => { cmd_cheapest(&mut client, format, fuels, count).await? }
+        Commands::Watch { name, addr } => { cmd_watch(&mut client, format, fuels, name, addr).await? }
+    }
 
     Ok(())
 }
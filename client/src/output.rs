@@ -0,0 +1,173 @@
+use crate::refuel;
+
+use chrono::{DateTime, Local, TimeZone};
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// Human-readable table
+    Table,
+    /// JSON document
+    Json,
+}
+
+#[derive(Clone, Copy, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Fuel {
+    /// Diesel
+    Diesel,
+    /// Super E5
+    E5,
+    /// Super E10
+    E10,
+    /// Liquefied petroleum gas, autogas
+    Lpg,
+    /// Compressed natural gas
+    Cng,
+}
+
+#[derive(Serialize)]
+pub struct Station {
+    id: i32,
+    name: String,
+    addr: String,
+}
+
+#[derive(Serialize)]
+pub struct PriceChange {
+    id: i32,
+    name: String,
+    addr: String,
+    fuel: Option<Fuel>,
+    updated: Option<DateTime<Local>>,
+    /// price in the currency unit, e.g. euro
+    price: f64,
+    currency: String,
+}
+
+impl From<refuel::Station> for Station {
+    fn from(src: refuel::Station) -> Self {
+        Self {
+            id: src.id,
+            name: src.name,
+            addr: src.addr,
+        }
+    }
+}
+
+impl From<refuel::PriceChange> for PriceChange {
+    fn from(src: refuel::PriceChange) -> Self {
+        let station = src.station.unwrap_or_default();
+        let updated = src.updated.and_then(|updated| {
+            let nanos = u32::try_from(updated.nanos).ok()?;
+            Local.timestamp_opt(updated.seconds, nanos).single()
+        });
+        Self {
+            id: station.id,
+            name: station.name,
+            addr: station.addr,
+            fuel: refuel::FuelType::from_i32(src.fuel).and_then(|fuel| fuel.try_into().ok()),
+            updated,
+            price: src.price as f64 / 1000.0,
+            currency: src.currency,
+        }
+    }
+}
+
+impl From<Fuel> for refuel::FuelType {
+    fn from(src: Fuel) -> Self {
+        match src {
+            Fuel::Diesel => Self::Diesel,
+            Fuel::E5 => Self::E5,
+            Fuel::E10 => Self::E10,
+            Fuel::Lpg => Self::Lpg,
+            Fuel::Cng => Self::Cng,
+        }
+    }
+}
+
+impl TryFrom<refuel::FuelType> for Fuel {
+    type Error = ();
+
+    fn try_from(src: refuel::FuelType) -> Result<Self, Self::Error> {
+        match src {
+            refuel::FuelType::Diesel => Ok(Self::Diesel),
+            refuel::FuelType::E5 => Ok(Self::E5),
+            refuel::FuelType::E10 => Ok(Self::E10),
+            refuel::FuelType::Lpg => Ok(Self::Lpg),
+            refuel::FuelType::Cng => Ok(Self::Cng),
+            refuel::FuelType::Unspecified => Err(()),
+        }
+    }
+}
+
+impl Fuel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Fuel::Diesel => "diesel",
+            Fuel::E5 => "e5",
+            Fuel::E10 => "e10",
+            Fuel::Lpg => "lpg",
+            Fuel::Cng => "cng",
+        }
+    }
+}
+
+pub trait Row {
+    const HEADER: &'static [&'static str];
+    fn cells(&self) -> Vec<String>;
+}
+
+impl Row for Station {
+    const HEADER: &'static [&'static str] = &["ID", "NAME", "ADDRESS"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.name.clone(), self.addr.clone()]
+    }
+}
+
+impl Row for PriceChange {
+    const HEADER: &'static [&'static str] = &["ID", "NAME", "ADDRESS", "FUEL", "UPDATED", "PRICE", "CURRENCY"];
+
+    fn cells(&self) -> Vec<String> {
+        let updated = self.updated.map_or_else(|| "-".to_owned(), |updated| updated.format("%Y-%m-%d %H:%M").to_string());
+        let fuel = self.fuel.map_or("-", |fuel| fuel.as_str()).to_owned();
+        vec![self.id.to_string(), self.name.clone(), self.addr.clone(), fuel, updated, format!("{:.3}", self.price), self.currency.clone()]
+    }
+}
+
+fn print_table<T: Row>(rows: &[T]) {
+    let rows: Vec<Vec<String>> = rows.iter().map(Row::cells).collect();
+    let mut widths: Vec<usize> = T::HEADER.iter().map(|header| header.chars().count()).collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header: Vec<String> = T::HEADER.iter().map(|header| header.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let line: Vec<String> = row.iter().zip(widths.iter())
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+pub fn print<T: Row + Serialize>(format: Format, rows: &[T]) -> serde_json::Result<()> {
+    match format {
+        Format::Table => print_table(rows),
+        Format::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+    }
+    Ok(())
+}
+
+/// Prints a single streamed price change, one line each
+pub fn print_change(format: Format, change: &PriceChange) -> serde_json::Result<()> {
+    match format {
+        Format::Table => println!("{}", change.cells().join("  ")),
+        Format::Json => println!("{}", serde_json::to_string(change)?),
+    }
+    Ok(())
+}
@@ -1,4 +1,6 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::compile_protos("../proto/service.proto")?;
+    // migrations are embedded into the binary
+    println!("cargo:rerun-if-changed=migrations");
     Ok(())
 }
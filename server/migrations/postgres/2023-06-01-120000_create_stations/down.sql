@@ -0,0 +1,22 @@
+ALTER TABLE price_changes
+    ADD COLUMN name VARCHAR,
+    ADD COLUMN addr VARCHAR;
+
+UPDATE price_changes SET name = stations.name, addr = stations.addr
+    FROM stations
+    WHERE stations.id = price_changes.station_id;
+
+-- linked stations may have saved the same time twice under the old name and address
+DELETE FROM price_changes AS duplicate
+    USING price_changes AS kept
+    WHERE duplicate.name = kept.name AND duplicate.addr = kept.addr AND duplicate.updated = kept.updated
+        AND duplicate.station_id > kept.station_id;
+
+ALTER TABLE price_changes
+    DROP CONSTRAINT price_changes_pkey,
+    DROP COLUMN station_id,
+    ALTER COLUMN name SET NOT NULL,
+    ALTER COLUMN addr SET NOT NULL,
+    ADD PRIMARY KEY (name, addr, updated);
+
+DROP TABLE stations;
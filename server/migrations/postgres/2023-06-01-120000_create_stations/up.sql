@@ -0,0 +1,23 @@
+CREATE TABLE stations (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    addr VARCHAR NOT NULL,
+    merged_into INTEGER REFERENCES stations (id),
+    UNIQUE (name, addr)
+);
+
+INSERT INTO stations (name, addr)
+    SELECT DISTINCT name, addr FROM price_changes ORDER BY name, addr;
+
+ALTER TABLE price_changes ADD COLUMN station_id INTEGER REFERENCES stations (id);
+
+UPDATE price_changes SET station_id = stations.id
+    FROM stations
+    WHERE stations.name = price_changes.name AND stations.addr = price_changes.addr;
+
+ALTER TABLE price_changes
+    DROP CONSTRAINT price_changes_pkey,
+    DROP COLUMN name,
+    DROP COLUMN addr,
+    ALTER COLUMN station_id SET NOT NULL,
+    ADD PRIMARY KEY (station_id, updated);
@@ -0,0 +1,6 @@
+DELETE FROM price_changes WHERE fuel <> 'e5';
+
+ALTER TABLE price_changes
+    DROP CONSTRAINT price_changes_pkey,
+    DROP COLUMN fuel,
+    ADD PRIMARY KEY (station_id, updated);
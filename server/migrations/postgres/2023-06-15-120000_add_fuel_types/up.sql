@@ -0,0 +1,7 @@
+-- all prices scraped so far are Super E5 prices
+ALTER TABLE price_changes ADD COLUMN fuel VARCHAR NOT NULL DEFAULT 'e5';
+
+ALTER TABLE price_changes
+    ALTER COLUMN fuel DROP DEFAULT,
+    DROP CONSTRAINT price_changes_pkey,
+    ADD PRIMARY KEY (station_id, fuel, updated);
@@ -0,0 +1,6 @@
+-- prices of other currencies or beyond the old range can't be told apart
+DELETE FROM price_changes WHERE currency <> 'EUR' OR price > 65535;
+
+ALTER TABLE price_changes
+    DROP COLUMN currency,
+    ALTER COLUMN price TYPE INTEGER;
@@ -0,0 +1,6 @@
+-- all prices scraped so far are euro prices
+ALTER TABLE price_changes ADD COLUMN currency VARCHAR NOT NULL DEFAULT 'EUR';
+
+ALTER TABLE price_changes
+    ALTER COLUMN currency DROP DEFAULT,
+    ALTER COLUMN price TYPE BIGINT;
@@ -0,0 +1,17 @@
+ALTER TABLE price_changes RENAME TO price_changes_new;
+
+CREATE TABLE price_changes (
+    name VARCHAR NOT NULL,
+    addr VARCHAR NOT NULL,
+    updated TIMESTAMP NOT NULL,
+    price INTEGER NOT NULL,
+    PRIMARY KEY (name, addr, updated)
+);
+
+INSERT OR IGNORE INTO price_changes (name, addr, updated, price)
+    SELECT stations.name, stations.addr, price_changes_new.updated, price_changes_new.price
+    FROM price_changes_new
+    JOIN stations ON stations.id = price_changes_new.station_id;
+
+DROP TABLE price_changes_new;
+DROP TABLE stations;
@@ -0,0 +1,26 @@
+CREATE TABLE stations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name VARCHAR NOT NULL,
+    addr VARCHAR NOT NULL,
+    merged_into INTEGER REFERENCES stations (id),
+    UNIQUE (name, addr)
+);
+
+INSERT INTO stations (name, addr)
+    SELECT DISTINCT name, addr FROM price_changes ORDER BY name, addr;
+
+ALTER TABLE price_changes RENAME TO price_changes_old;
+
+CREATE TABLE price_changes (
+    station_id INTEGER NOT NULL REFERENCES stations (id),
+    updated TIMESTAMP NOT NULL,
+    price INTEGER NOT NULL,
+    PRIMARY KEY (station_id, updated)
+);
+
+INSERT INTO price_changes (station_id, updated, price)
+    SELECT stations.id, price_changes_old.updated, price_changes_old.price
+    FROM price_changes_old
+    JOIN stations ON stations.name = price_changes_old.name AND stations.addr = price_changes_old.addr;
+
+DROP TABLE price_changes_old;
@@ -0,0 +1,13 @@
+ALTER TABLE price_changes RENAME TO price_changes_new;
+
+CREATE TABLE price_changes (
+    station_id INTEGER NOT NULL REFERENCES stations (id),
+    updated TIMESTAMP NOT NULL,
+    price INTEGER NOT NULL,
+    PRIMARY KEY (station_id, updated)
+);
+
+INSERT INTO price_changes (station_id, updated, price)
+    SELECT station_id, updated, price FROM price_changes_new WHERE fuel = 'e5';
+
+DROP TABLE price_changes_new;
@@ -0,0 +1,15 @@
+ALTER TABLE price_changes RENAME TO price_changes_old;
+
+CREATE TABLE price_changes (
+    station_id INTEGER NOT NULL REFERENCES stations (id),
+    fuel VARCHAR NOT NULL,
+    updated TIMESTAMP NOT NULL,
+    price INTEGER NOT NULL,
+    PRIMARY KEY (station_id, fuel, updated)
+);
+
+-- all prices scraped so far are Super E5 prices
+INSERT INTO price_changes (station_id, fuel, updated, price)
+    SELECT station_id, 'e5', updated, price FROM price_changes_old;
+
+DROP TABLE price_changes_old;
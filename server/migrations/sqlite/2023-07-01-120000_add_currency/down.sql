@@ -0,0 +1,16 @@
+ALTER TABLE price_changes RENAME TO price_changes_new;
+
+CREATE TABLE price_changes (
+    station_id INTEGER NOT NULL REFERENCES stations (id),
+    fuel VARCHAR NOT NULL,
+    updated TIMESTAMP NOT NULL,
+    price INTEGER NOT NULL,
+    PRIMARY KEY (station_id, fuel, updated)
+);
+
+-- prices of other currencies or beyond the old range can't be told apart
+INSERT INTO price_changes (station_id, fuel, updated, price)
+    SELECT station_id, fuel, updated, price FROM price_changes_new
+    WHERE currency = 'EUR' AND price <= 65535;
+
+DROP TABLE price_changes_new;
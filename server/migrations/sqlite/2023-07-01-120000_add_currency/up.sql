@@ -0,0 +1,16 @@
+ALTER TABLE price_changes RENAME TO price_changes_old;
+
+CREATE TABLE price_changes (
+    station_id INTEGER NOT NULL REFERENCES stations (id),
+    fuel VARCHAR NOT NULL,
+    updated TIMESTAMP NOT NULL,
+    price BIGINT NOT NULL,
+    currency VARCHAR NOT NULL,
+    PRIMARY KEY (station_id, fuel, updated)
+);
+
+-- all prices scraped so far are euro prices
+INSERT INTO price_changes (station_id, fuel, updated, price, currency)
+    SELECT station_id, fuel, updated, price, 'EUR' FROM price_changes_old;
+
+DROP TABLE price_changes_old;
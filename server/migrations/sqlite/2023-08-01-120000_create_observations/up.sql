@@ -0,0 +1,10 @@
+-- every price seen by a scrape, while price_changes only keeps the ones differing from the last known price
+CREATE TABLE observations (
+    station_id INTEGER NOT NULL REFERENCES stations (id),
+    fuel VARCHAR NOT NULL,
+    observed TIMESTAMP NOT NULL,
+    updated TIMESTAMP NOT NULL,
+    price BIGINT NOT NULL,
+    currency VARCHAR NOT NULL,
+    PRIMARY KEY (station_id, fuel, observed)
+);
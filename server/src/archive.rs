@@ -0,0 +1,154 @@
+use crate::error::ArchiveError;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::task;
+use url::Url;
+
+use tracing::{debug, info, Span};
+
+type Result<T> = std::result::Result<T, ArchiveError>;
+
+const INDEX: &str = "index.jsonl";
+const OBJECTS: &str = "objects";
+
+/// Raw documents of all scrapes, to parse them again after a parser fix.
+/// Every document is stored once, gzip compressed, as `objects/<ab>/<abcd..>.gz` named by
+/// its SHA-256 hash, while `index.jsonl` lists when which url responded with which document.
+#[derive(Clone)]
+pub(crate) struct Archive {
+    dir: PathBuf,
+    /// Hash of the latest archived document of each url
+    latest: Arc<Mutex<HashMap<Url, String>>>,
+}
+
+/// Line of the index
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ArchiveEntry {
+    pub fetched: DateTime<Utc>,
+    pub url: Url,
+    /// Hex encoded SHA-256 hash of the document
+    pub hash: String,
+}
+
+impl Archive {
+    /// Opens the archive in `dir`, which is created if missing
+    #[tracing::instrument]
+    pub(crate) fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir.join(OBJECTS)).map_err(|source| ArchiveError::Io { path: dir.to_owned(), source })?;
+        let archive = Self { dir: dir.to_owned(), latest: Arc::new(Mutex::new(HashMap::new())) };
+        let entries = archive.entries()?;
+        info!("archive opened with {} entries", entries.len());
+        *archive.latest.lock().unwrap_or_else(PoisonError::into_inner) = entries.into_iter()
+            .map(|entry| (entry.url, entry.hash))
+            .collect();
+        Ok(archive)
+    }
+
+    /// Archives a document fetched from `url`, unless it's the same as the one fetched before.
+    /// Returns whether it was archived. Compressing and syncing it to disk run on a thread
+    /// for blocking tasks, so they don't hold up the scrapes of other targets.
+    #[tracing::instrument(skip(self, document))]
+    pub(crate) async fn store(&self, url: &Url, fetched: DateTime<Utc>, document: &str) -> Result<bool> {
+        let (archive, url, document) = (self.clone(), url.clone(), document.to_owned());
+        let span = Span::current();
+        match task::spawn_blocking(move || span.in_scope(|| archive.store_blocking(&url, fetched, &document))).await {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+
+    fn store_blocking(&self, url: &Url, fetched: DateTime<Utc>, document: &str) -> Result<bool> {
+        let hash = hash(document.as_bytes());
+        let mut latest = self.latest.lock().unwrap_or_else(PoisonError::into_inner);
+        if latest.get(url) == Some(&hash) {
+            debug!("document {hash} unchanged, not archived");
+            return Ok(false);
+        }
+
+        let path = self.object_path(&hash);
+        if !path.exists() {
+            self.write_object(&path, document)?;
+        }
+        let entry = ArchiveEntry { fetched, url: url.clone(), hash };
+        let mut line = serde_json::to_string(&entry).expect("archive entry not serializable");
+        line.push('\n');
+        let index = self.dir.join(INDEX);
+        OpenOptions::new().create(true).append(true).open(&index)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|source| ArchiveError::Io { path: index, source })?;
+
+        debug!("document {} archived", entry.hash);
+        latest.insert(entry.url, entry.hash);
+        Ok(true)
+    }
+
+    /// All entries of the index, oldest first
+    pub(crate) fn entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let path = self.dir.join(INDEX);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(ArchiveError::Io { path, source }),
+        };
+        let mut entries = Vec::new();
+        for (line, text) in BufReader::new(file).lines().enumerate() {
+            let text = text.map_err(|source| ArchiveError::Io { path: path.clone(), source })?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&text)
+                .map_err(|source| ArchiveError::Index { path: path.clone(), line: line + 1, source })?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Archived document with the given hash
+    pub(crate) fn load(&self, hash: &str) -> Result<String> {
+        let path = self.object_path(hash);
+        let mut document = String::new();
+        File::open(&path)
+            .and_then(|file| GzDecoder::new(file).read_to_string(&mut document))
+            .map_err(|source| ArchiveError::Io { path, source })?;
+        if self::hash(document.as_bytes()) != hash {
+            return Err(ArchiveError::Corrupt { hash: hash.to_owned() });
+        }
+        Ok(document)
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(OBJECTS).join(&hash[..2.min(hash.len())]).join(format!("{hash}.gz"))
+    }
+
+    /// Writes a temporary file renamed once complete, so an object is never partially written
+    fn write_object(&self, path: &Path, document: &str) -> Result<()> {
+        let temp = path.with_extension("gz.tmp");
+        let write = || {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut encoder = GzEncoder::new(File::create(&temp)?, Compression::best());
+            encoder.write_all(document.as_bytes())?;
+            encoder.finish()?.sync_all()?;
+            fs::rename(&temp, path)
+        };
+        write().map_err(|source| ArchiveError::Io { path: path.to_owned(), source })
+    }
+}
+
+fn hash(document: &[u8]) -> String {
+    Sha256::digest(document).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,112 @@
+use super::*;
+use crate::testing::scratch;
+use chrono::TimeZone;
+
+/// SHA-256 hash of "abc"
+const ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+fn url(fuel: &str) -> Url {
+    Url::parse(&format!("http://localhost:8080/?fuel={fuel}")).unwrap()
+}
+
+fn fetched(minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 18, 9, minute, 0).unwrap()
+}
+
+/// Number of archived objects
+fn objects(dir: &Path) -> usize {
+    fs::read_dir(dir.join(OBJECTS)).unwrap()
+        .map(|prefix| fs::read_dir(prefix.unwrap().path()).unwrap().count())
+        .sum()
+}
+
+#[tokio::test]
+async fn documents_are_stored_by_their_hash() {
+    let dir = scratch("archive-objects");
+    let archive = Archive::open(&dir).unwrap();
+    assert!(archive.entries().unwrap().is_empty());
+    assert!(archive.store(&url("e5"), fetched(0), "abc").await.unwrap());
+
+    let object = dir.join(OBJECTS).join("ba").join(format!("{ABC}.gz"));
+    let mut stored = String::new();
+    GzDecoder::new(File::open(object).unwrap()).read_to_string(&mut stored).unwrap();
+    assert_eq!(stored, "abc");
+    assert_eq!(archive.load(ABC).unwrap(), "abc");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn unchanged_documents_are_stored_once() {
+    let dir = scratch("archive-unchanged");
+    let archive = Archive::open(&dir).unwrap();
+    assert!(archive.store(&url("e5"), fetched(0), "abc").await.unwrap());
+    assert!(!archive.store(&url("e5"), fetched(20), "abc").await.unwrap());
+    // the same document of another url or after another one is listed again, but stored once
+    assert!(archive.store(&url("e10"), fetched(20), "abc").await.unwrap());
+    assert!(archive.store(&url("e5"), fetched(40), "abcd").await.unwrap());
+    assert!(archive.store(&url("e5"), fetched(59), "abc").await.unwrap());
+
+    let entries: Vec<_> = archive.entries().unwrap().into_iter()
+        .map(|entry| (entry.fetched, entry.url.query().unwrap().to_owned(), entry.hash == ABC))
+        .collect();
+    assert_eq!(entries, vec![
+        (fetched(0), "fuel=e5".to_owned(), true),
+        (fetched(20), "fuel=e10".to_owned(), true),
+        (fetched(40), "fuel=e5".to_owned(), false),
+        (fetched(59), "fuel=e5".to_owned(), true),
+    ]);
+    assert_eq!(objects(&dir), 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn reopened_archives_know_their_entries() {
+    let dir = scratch("archive-reopened");
+    let archive = Archive::open(&dir).unwrap();
+    archive.store(&url("e5"), fetched(0), "abc").await.unwrap();
+    archive.store(&url("e10"), fetched(1), "abcd").await.unwrap();
+    archive.store(&url("e5"), fetched(20), "abcde").await.unwrap();
+
+    let reopened = Archive::open(&dir).unwrap();
+    let entries = reopened.entries().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!((entries[0].fetched, &entries[0].url, entries[0].hash.as_str()), (fetched(0), &url("e5"), ABC));
+    // the latest document of each url is still known
+    assert!(!reopened.store(&url("e5"), fetched(40), "abcde").await.unwrap());
+    assert!(!reopened.store(&url("e10"), fetched(40), "abcd").await.unwrap());
+    assert!(reopened.store(&url("e5"), fetched(40), "abc").await.unwrap());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn invalid_index_lines_are_reported() {
+    let dir = scratch("archive-invalid-index");
+    let archive = Archive::open(&dir).unwrap();
+    archive.store(&url("e5"), fetched(0), "abc").await.unwrap();
+    let mut index = OpenOptions::new().append(true).open(dir.join(INDEX)).unwrap();
+    index.write_all(b"\n{\"fetched\":\"yesterday\"}\n").unwrap();
+
+    let err = archive.entries().unwrap_err();
+    assert!(matches!(err, ArchiveError::Index { line: 3, .. }), "{err}");
+    assert!(matches!(Archive::open(&dir), Err(ArchiveError::Index { line: 3, .. })));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn changed_objects_are_corrupt() {
+    let dir = scratch("archive-corrupt");
+    let archive = Archive::open(&dir).unwrap();
+    archive.store(&url("e5"), fetched(0), "abc").await.unwrap();
+    let object = dir.join(OBJECTS).join("ba").join(format!("{ABC}.gz"));
+
+    let mut encoder = GzEncoder::new(File::create(&object).unwrap(), Compression::default());
+    encoder.write_all(b"abd").unwrap();
+    encoder.finish().unwrap();
+    assert!(matches!(archive.load(ABC), Err(ArchiveError::Corrupt { hash }) if hash == ABC));
+
+    fs::write(&object, "abc").unwrap();
+    assert!(matches!(archive.load(ABC), Err(ArchiveError::Io { .. })));
+    fs::remove_file(&object).unwrap();
+    assert!(matches!(archive.load(ABC), Err(ArchiveError::Io { path, .. }) if path == object));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,171 @@
+use crate::download::{fuel_url, DownloadSettings};
+use crate::error::ConfigError;
+use crate::fuel::FuelType;
+use crate::profile::SiteProfile;
+use crate::schedule::Schedule;
+use crate::source::SourceKind;
+
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+use tracing::info;
+
+type Result<T> = std::result::Result<T, ConfigError>;
+
+/// Default time between two scrapes of the same target
+pub(crate) const DEFAULT_INTERVAL: Duration = Duration::from_secs(20 * 60);
+/// Default number of targets scraped at the same time
+pub(crate) const DEFAULT_CONCURRENCY: usize = 4;
+/// Default time zone of the times shown in price lists
+pub(crate) const DEFAULT_TIMEZONE: Tz = chrono_tz::Europe::Berlin;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Maximum number of targets scraped at the same time
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    /// Jitter, bounds and quiet hours applied to the intervals of all targets
+    #[serde(default)]
+    pub schedule: Schedule,
+    /// Timeouts and retries of all downloads
+    #[serde(default)]
+    pub download: DownloadSettings,
+    /// Directory archiving every fetched document, relative to the config file
+    #[serde(default)]
+    pub archive: Option<PathBuf>,
+    #[serde(rename = "target")]
+    pub targets: Vec<Target>,
+}
+
+/// A price list to scrape
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Target {
+    pub url: Url,
+    /// Label of the region the price list covers, e.g. the city
+    #[serde(default)]
+    pub region: Option<String>,
+    pub fuel: FuelType,
+    /// Layout or API of the document at `url`
+    #[serde(default)]
+    pub source: SourceKind,
+    /// Site profile file with the selectors and regexes of an html price list,
+    /// relative to the config file
+    #[serde(default)]
+    pub profile: Option<PathBuf>,
+    /// The loaded site profile, the builtin one if no file is given
+    #[serde(skip, default = "SiteProfile::builtin")]
+    pub site_profile: Arc<SiteProfile>,
+    /// IANA time zone of the times shown in the price list, e.g. `Europe/Berlin`
+    #[serde(default = "default_timezone")]
+    pub timezone: Tz,
+    #[serde(default = "default_interval", with = "humantime_serde")]
+    pub interval: Duration,
+}
+
+fn default_concurrency() -> usize {
+    DEFAULT_CONCURRENCY
+}
+
+fn default_interval() -> Duration {
+    DEFAULT_INTERVAL
+}
+
+fn default_timezone() -> Tz {
+    DEFAULT_TIMEZONE
+}
+
+impl Config {
+    #[tracing::instrument]
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let config = fs::read_to_string(path).map_err(|source| ConfigError::Io { path: path.to_owned(), source })?;
+        let mut config: Self = toml::from_str(&config).map_err(|source| ConfigError::Toml { path: path.to_owned(), source })?;
+        config.validate()?;
+        if let Some(dir) = path.parent() {
+            for target in config.targets.iter_mut() {
+                target.profile = target.profile.as_ref().map(|profile| dir.join(profile));
+            }
+            config.archive = config.archive.as_ref().map(|archive| dir.join(archive));
+            let download = &mut config.download;
+            for root_certificate in download.root_certificates.iter_mut() {
+                *root_certificate = dir.join(&root_certificate);
+            }
+            download.client_certificate = download.client_certificate.as_ref().map(|certificate| dir.join(certificate));
+            download.client_key = download.client_key.as_ref().map(|key| dir.join(key));
+        }
+        config.load_profiles()?;
+        info!("config loaded with {} targets", config.targets.len());
+        Ok(config)
+    }
+
+    /// Config with one target per fuel type of a single webapp
+    pub(crate) fn from_url(url: &Url, fuels: &[FuelType], source: SourceKind, profile: Option<&Path>,
+                           timezone: Tz, interval: Duration, schedule: Schedule) -> Result<Self> {
+        let targets = fuels.iter()
+            .map(|fuel| Target {
+                url: fuel_url(url, source, *fuel),
+                region: None,
+                fuel: *fuel,
+                source,
+                profile: profile.map(Path::to_owned),
+                site_profile: SiteProfile::builtin(),
+                timezone,
+                interval,
+            })
+            .collect();
+        let mut config = Self { concurrency: DEFAULT_CONCURRENCY, schedule, download: DownloadSettings::default(),
+                               archive: None, targets };
+        config.validate()?;
+        config.load_profiles()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.concurrency == 0 {
+            return Err(ConfigError::Invalid("concurrency must be at least 1".to_owned()));
+        }
+        if self.targets.is_empty() {
+            return Err(ConfigError::Invalid("no targets configured".to_owned()));
+        }
+        if let Some(target) = self.targets.iter().find(|target| target.interval.is_zero()) {
+            return Err(ConfigError::Invalid(format!("interval of target {} must not be zero", target.url)));
+        }
+        if let Some(target) = self.targets.iter().find(|target| target.profile.is_some() && target.source != SourceKind::PriceList) {
+            return Err(ConfigError::Invalid(format!("site profile of target {} requires the price-list source", target.url)));
+        }
+        if self.download.client_certificate.is_some() != self.download.client_key.is_some() {
+            return Err(ConfigError::Invalid("client_certificate and client_key must be given together".to_owned()));
+        }
+        self.schedule.validate().map_err(ConfigError::Invalid)?;
+        Ok(())
+    }
+
+    /// Loads and validates the site profiles of all targets, each file only once
+    fn load_profiles(&mut self) -> Result<()> {
+        let mut profiles: HashMap<PathBuf, Arc<SiteProfile>> = HashMap::new();
+        for target in self.targets.iter_mut() {
+            let Some(path) = target.profile.as_ref() else { continue };
+            target.site_profile = match profiles.get(path) {
+                Some(profile) => profile.clone(),
+                None => {
+                    let profile = SiteProfile::load(path)
+                        .map_err(|source| ConfigError::Profile { url: target.url.clone(), source: Box::new(source) })?;
+                    let profile = Arc::new(profile);
+                    profiles.insert(path.clone(), profile.clone());
+                    profile
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
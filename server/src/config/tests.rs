@@ -0,0 +1,147 @@
+use super::*;
+use crate::error::ParseError;
+use crate::testing::scratch;
+
+/// Loads `config` written to `refuel.toml` of a scratch directory
+fn load(name: &str, config: &str) -> Result<Config> {
+    let dir = scratch(name);
+    fs::write(dir.join("refuel.toml"), config).unwrap();
+    let loaded = Config::load(&dir.join("refuel.toml"));
+    fs::remove_dir_all(dir).unwrap();
+    loaded
+}
+
+fn invalid(name: &str, config: &str) -> String {
+    match load(name, config) {
+        Err(ConfigError::Invalid(message)) => message,
+        Err(err) => panic!("unexpected error: {err}"),
+        Ok(_) => panic!("invalid config loaded"),
+    }
+}
+
+const TARGET: &str = r#"
+[[target]]
+url = "http://localhost:8080/?fuel=e5"
+fuel = "e5"
+"#;
+
+#[test]
+fn example_config_is_loaded() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let config = Config::load(&dir.join("refuel.example.toml")).unwrap();
+    assert_eq!(config.concurrency, 4);
+    assert_eq!(config.schedule.quiet_hours.len(), 1);
+    assert_eq!(config.schedule.rush_hours.len(), 2);
+    assert_eq!(config.download.host_interval, Duration::from_secs(5));
+    let targets: Vec<_> = config.targets.iter().map(|target| (target.fuel, target.interval)).collect();
+    assert_eq!(targets, vec![
+        (FuelType::E5, Duration::from_secs(20 * 60)),
+        (FuelType::E10, Duration::from_secs(20 * 60)),
+        (FuelType::Diesel, Duration::from_secs(30 * 60)),
+    ]);
+    assert_eq!(config.targets[0].profile, Some(dir.join("profile.example.toml")));
+    assert_eq!(config.targets[0].region.as_deref(), Some("Berlin"));
+}
+
+#[test]
+fn missing_settings_are_defaulted() {
+    let config = load("config-defaults", TARGET).unwrap();
+    assert_eq!(config.concurrency, DEFAULT_CONCURRENCY);
+    assert!(config.archive.is_none());
+    let target = &config.targets[0];
+    assert_eq!(target.source, SourceKind::PriceList);
+    assert_eq!(target.timezone, DEFAULT_TIMEZONE);
+    assert_eq!(target.interval, DEFAULT_INTERVAL);
+    assert!(Arc::ptr_eq(&target.site_profile, &SiteProfile::builtin()));
+}
+
+#[test]
+fn paths_are_relative_to_the_config_file() {
+    let dir = scratch("config-paths");
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("profile.example.toml"), dir.join("profile.toml")).unwrap();
+    fs::write(dir.join("refuel.toml"), format!(r#"
+archive = "archive"
+
+[download]
+root_certificates = ["proxy-ca.pem"]
+client_certificate = "client.pem"
+client_key = "client.key"
+
+[[target]]
+url = "http://localhost:8080/?fuel=e5"
+fuel = "e5"
+profile = "profile.toml"
+
+[[target]]
+url = "http://localhost:8080/?fuel=e10"
+fuel = "e10"
+profile = "profile.toml"
+{TARGET}"#)).unwrap();
+
+    let config = Config::load(&dir.join("refuel.toml")).unwrap();
+    assert_eq!(config.archive, Some(dir.join("archive")));
+    assert_eq!(config.download.root_certificates, vec![dir.join("proxy-ca.pem")]);
+    assert_eq!(config.download.client_certificate, Some(dir.join("client.pem")));
+    assert_eq!(config.download.client_key, Some(dir.join("client.key")));
+    assert_eq!(config.targets[0].profile, Some(dir.join("profile.toml")));
+    // a profile file shared by targets is loaded once
+    assert!(Arc::ptr_eq(&config.targets[0].site_profile, &config.targets[1].site_profile));
+    assert!(!Arc::ptr_eq(&config.targets[0].site_profile, &SiteProfile::builtin()));
+    assert!(config.targets[2].profile.is_none());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unreadable_files_are_rejected() {
+    let path = scratch("config-missing").join("refuel.toml");
+    assert!(matches!(Config::load(&path), Err(ConfigError::Io { path: missing, .. }) if missing == path));
+}
+
+#[test]
+fn invalid_toml_is_rejected() {
+    assert!(matches!(load("config-syntax", "[[target]\nfuel = \"e5\""), Err(ConfigError::Toml { .. })));
+    assert!(matches!(load("config-unknown-field", &format!("interval = \"5m\"\n{TARGET}")), Err(ConfigError::Toml { .. })));
+    assert!(matches!(load("config-unknown-fuel", &TARGET.replace("\"e5\"\n", "\"e85\"\n")), Err(ConfigError::Toml { .. })));
+    assert!(matches!(load("config-timezone", &format!("{TARGET}timezone = \"Europe/Atlantis\"")), Err(ConfigError::Toml { .. })));
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    assert_eq!(invalid("config-concurrency", &format!("concurrency = 0\n{TARGET}")), "concurrency must be at least 1");
+    assert_eq!(invalid("config-targets", "target = []"), "no targets configured");
+    assert_eq!(invalid("config-interval", &format!("{TARGET}interval = \"0s\"")),
+               "interval of target http://localhost:8080/?fuel=e5 must not be zero");
+    assert_eq!(invalid("config-profile-source", &format!("{TARGET}source = \"tankerkoenig\"\nprofile = \"profile.toml\"")),
+               "site profile of target http://localhost:8080/?fuel=e5 requires the price-list source");
+    assert_eq!(invalid("config-client-key", &format!("[download]\nclient_certificate = \"client.pem\"\n{TARGET}")),
+               "client_certificate and client_key must be given together");
+    invalid("config-schedule", &format!("[schedule]\nmin = \"0s\"\n{TARGET}"));
+}
+
+#[test]
+fn invalid_site_profiles_are_rejected() {
+    let dir = scratch("config-invalid-profile");
+    fs::write(dir.join("profile.toml"), "[regexes]\nprice = '(?P<int>\\d+'").unwrap();
+    fs::write(dir.join("refuel.toml"), format!("{TARGET}profile = \"profile.toml\"")).unwrap();
+    let loaded = Config::load(&dir.join("refuel.toml"));
+    assert!(matches!(loaded, Err(ConfigError::Profile { source, .. }) if matches!(*source, ParseError::InvalidRegexError { name: "price", .. })));
+
+    fs::write(dir.join("refuel.toml"), format!("{TARGET}profile = \"missing.toml\"")).unwrap();
+    let loaded = Config::load(&dir.join("refuel.toml"));
+    assert!(matches!(loaded, Err(ConfigError::Profile { source, .. }) if matches!(*source, ParseError::ProfileIoError { .. })));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn single_webapps_get_one_target_per_fuel() {
+    let url = Url::parse("http://localhost:8080/").unwrap();
+    let config = Config::from_url(&url, &[FuelType::E5, FuelType::Diesel], SourceKind::PriceList, None,
+                                  DEFAULT_TIMEZONE, DEFAULT_INTERVAL, Schedule::default()).unwrap();
+    let urls: Vec<_> = config.targets.iter().map(|target| target.url.as_str()).collect();
+    assert_eq!(urls, vec!["http://localhost:8080/?fuel=e5", "http://localhost:8080/?fuel=diesel"]);
+
+    let profile = Path::new("profile.toml");
+    let config = Config::from_url(&url, &[FuelType::E5], SourceKind::Tankerkoenig, Some(profile),
+                                  DEFAULT_TIMEZONE, DEFAULT_INTERVAL, Schedule::default());
+    assert!(matches!(config, Err(ConfigError::Invalid(_))));
+}
@@ -0,0 +1,347 @@
+use crate::error::StorageError;
+
+use diesel::connection::{SimpleConnection, TransactionManager};
+use diesel::migration::MigrationSource;
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool, PoolError, PooledConnection, R2D2Connection};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use dotenvy::dotenv;
+use std::collections::BTreeSet;
+use std::env;
+use std::time::Duration;
+use tokio::task;
+use url::Url;
+
+use tracing::{info, Span};
+
+type Result<T> = std::result::Result<T, StorageError>;
+
+/// Connection pool of a SQLite or, built with the `postgres` feature, a PostgreSQL database
+#[derive(Clone)]
+pub(crate) enum DbPool {
+    Sqlite(Pool<ConnectionManager<SqliteConnection>>),
+    #[cfg(feature = "postgres")]
+    Postgres(Pool<ConnectionManager<PgConnection>>),
+}
+
+/// Connection taken from a [`DbPool`]
+pub(crate) enum DbConnection {
+    Sqlite(PooledConnection<ConnectionManager<SqliteConnection>>),
+    #[cfg(feature = "postgres")]
+    Postgres(PooledConnection<ConnectionManager<PgConnection>>),
+}
+
+/// Evaluates `$body` with `$inner` bound to the diesel connection of the [`DbConnection`] `$conn`.
+/// The body is expanded once per backend, so its queries are checked against each of them.
+macro_rules! with_backend {
+    ($conn:expr, $inner:ident => $body:expr) => {
+        match $conn {
+            $crate::db::DbConnection::Sqlite($inner) => $body,
+            #[cfg(feature = "postgres")]
+            $crate::db::DbConnection::Postgres($inner) => $body,
+        }
+    };
+}
+pub(crate) use with_backend;
+
+/// Migrations of `migrations/sqlite` and `migrations/postgres`, embedded at build time
+const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/sqlite");
+#[cfg(feature = "postgres")]
+const POSTGRES_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/postgres");
+
+diesel::table! {
+    /// Versions of the applied migrations, as kept by diesel
+    __diesel_schema_migrations (version) {
+        version -> VarChar,
+    }
+}
+
+/// Migration of the database schema
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Migration {
+    /// Name like `2023-07-01-120000_add_currency`, only the version for unknown migrations
+    pub name: String,
+    pub status: MigrationStatus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum MigrationStatus {
+    Applied,
+    Pending,
+    /// Applied, but not embedded into this binary, so a newer one migrated the database
+    Unknown,
+}
+
+/// Maximum number of connections shared by the scraper and the gRPC service
+const POOL_SIZE: u32 = 8;
+/// Time a SQLite connection waits for the lock of another one before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl DbPool {
+    pub(crate) fn get(&self) -> std::result::Result<DbConnection, PoolError> {
+        Ok(match self {
+            DbPool::Sqlite(pool) => DbConnection::Sqlite(pool.get()?),
+            #[cfg(feature = "postgres")]
+            DbPool::Postgres(pool) => DbConnection::Postgres(pool.get()?),
+        })
+    }
+
+    /// Runs `f` with a connection on a thread for blocking tasks, as waiting for a connection and
+    /// queries block, which would stall the scraper and the gRPC service sharing the runtime
+    pub(crate) async fn run<T, E, F>(&self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(&mut DbConnection) -> std::result::Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<PoolError> + Send + 'static,
+    {
+        let pool = self.clone();
+        let span = Span::current();
+        match task::spawn_blocking(move || span.in_scope(|| f(&mut pool.get()?))).await {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+impl DbConnection {
+    /// Runs `f` in a transaction, which is committed if `f` succeeds and rolled back otherwise
+    pub(crate) fn transaction<T, E, F>(&mut self, f: F) -> std::result::Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> std::result::Result<T, E>,
+        E: From<diesel::result::Error>,
+    {
+        with_backend!(self, conn => begin(conn))?;
+        match f(self) {
+            Ok(value) => {
+                with_backend!(self, conn => commit(conn))?;
+                Ok(value)
+            }
+            Err(err) => match with_backend!(self, conn => rollback(conn)) {
+                Ok(()) | Err(diesel::result::Error::BrokenTransactionManager) => Err(err),
+                Err(rollback_err) => Err(rollback_err.into()),
+            },
+        }
+    }
+}
+
+impl DbConnection {
+    fn embedded_migrations(&self) -> EmbeddedMigrations {
+        match self {
+            DbConnection::Sqlite(_) => SQLITE_MIGRATIONS,
+            #[cfg(feature = "postgres")]
+            DbConnection::Postgres(_) => POSTGRES_MIGRATIONS,
+        }
+    }
+
+    /// Embedded migrations and any unknown applied ones, by version
+    pub(crate) fn migrations(&mut self) -> Result<Vec<Migration>> {
+        let embedded = self.embedded_migrations();
+        let embedded = with_backend!(self, conn => names(conn, &embedded))?;
+        // a database never migrated lacks the table of applied versions
+        let query = __diesel_schema_migrations::table.select(__diesel_schema_migrations::version);
+        let mut applied: BTreeSet<String> = match with_backend!(self, conn => query.load(conn)) {
+            Ok(applied) => applied,
+            Err(diesel::result::Error::DatabaseError(..)) => Vec::new(),
+            Err(err) => return Err(err.into()),
+        }.into_iter().collect();
+
+        let mut migrations: Vec<Migration> = embedded.into_iter()
+            .map(|(version, name)| {
+                let status = if applied.remove(&version) { MigrationStatus::Applied } else { MigrationStatus::Pending };
+                Migration { name, status }
+            })
+            .collect();
+        migrations.extend(applied.into_iter().map(|version| Migration { name: version, status: MigrationStatus::Unknown }));
+        migrations.sort_by_key(|migration| version(&migration.name));
+        Ok(migrations)
+    }
+
+    /// Applies the pending migrations, returns their names.
+    /// Fails for a schema migrated by a newer binary without changing it.
+    pub(crate) fn migrate(&mut self) -> Result<Vec<String>> {
+        let pending = self.check_known_schema()?;
+        if !pending.is_empty() {
+            let embedded = self.embedded_migrations();
+            with_backend!(self, conn => conn.run_pending_migrations(embedded).map(|_| ()))
+                .map_err(StorageError::Migration)?;
+        }
+        Ok(pending)
+    }
+
+    /// Reverts the latest applied migration, returns its name
+    pub(crate) fn revert_migration(&mut self) -> Result<Option<String>> {
+        self.check_known_schema()?;
+        let Some(latest) = self.migrations()?.into_iter().rev().find(|migration| migration.status == MigrationStatus::Applied) else {
+            return Ok(None);
+        };
+        let embedded = self.embedded_migrations();
+        with_backend!(self, conn => conn.revert_last_migration(embedded).map(|_| ()))
+            .map_err(StorageError::Migration)?;
+        Ok(Some(latest.name))
+    }
+
+    /// Fails unless the schema is exactly the one of this binary, without changing it
+    pub(crate) fn check_schema(&mut self) -> Result<()> {
+        let pending = self.check_known_schema()?;
+        if !pending.is_empty() {
+            return Err(StorageError::PendingMigrations { migrations: pending.join(", ") });
+        }
+        Ok(())
+    }
+
+    /// Names of the pending migrations, fails if there are unknown ones
+    fn check_known_schema(&mut self) -> Result<Vec<String>> {
+        let migrations = self.migrations()?;
+        let unknown: Vec<&str> = migrations.iter()
+            .filter(|migration| migration.status == MigrationStatus::Unknown)
+            .map(|migration| migration.name.as_str())
+            .collect();
+        if !unknown.is_empty() {
+            return Err(StorageError::NewerSchema { migrations: unknown.join(", ") });
+        }
+        Ok(migrations.into_iter()
+            .filter(|migration| migration.status == MigrationStatus::Pending)
+            .map(|migration| migration.name)
+            .collect())
+    }
+}
+
+/// Versions and names of the migrations of `source`
+fn names<C: Connection>(_: &C, source: &EmbeddedMigrations) -> Result<Vec<(String, String)>>
+where
+    EmbeddedMigrations: MigrationSource<C::Backend>,
+{
+    let migrations = MigrationSource::<C::Backend>::migrations(source).map_err(StorageError::Migration)?;
+    Ok(migrations.iter().map(|migration| (migration.name().version().to_string(), migration.name().to_string())).collect())
+}
+
+/// Version of a migration name like `2023-07-01-120000_add_currency`, which is `20230701120000`
+fn version(name: &str) -> String {
+    name.split('_').next().unwrap_or(name).replace('-', "")
+}
+
+fn begin<C: Connection>(conn: &mut C) -> diesel::QueryResult<()> {
+    C::TransactionManager::begin_transaction(conn)
+}
+
+fn commit<C: Connection>(conn: &mut C) -> diesel::QueryResult<()> {
+    C::TransactionManager::commit_transaction(conn)
+}
+
+fn rollback<C: Connection>(conn: &mut C) -> diesel::QueryResult<()> {
+    C::TransactionManager::rollback_transaction(conn)
+}
+
+/// Settings of every pooled connection
+#[derive(Debug)]
+struct ConnectionOptions {
+    read_only: bool,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> std::result::Result<(), diesel::r2d2::Error> {
+        conn.batch_execute(&format!("PRAGMA busy_timeout = {};", BUSY_TIMEOUT.as_millis()))
+            .map_err(diesel::r2d2::Error::QueryError)?;
+        if !self.read_only {
+            // readers like the gRPC service don't block the scraper writing and vice versa,
+            // the journal mode is kept by the database file
+            conn.batch_execute("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+                .map_err(diesel::r2d2::Error::QueryError)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl CustomizeConnection<PgConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, conn: &mut PgConnection) -> std::result::Result<(), diesel::r2d2::Error> {
+        if self.read_only {
+            conn.batch_execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY;")
+                .map_err(diesel::r2d2::Error::QueryError)?;
+        }
+        Ok(())
+    }
+}
+
+/// Connection pool of the database at `DATABASE_URL`, after applying the pending migrations
+pub(crate) fn pool() -> Result<DbPool> {
+    let pool = unmigrated_pool()?;
+    let applied = pool.get()?.migrate()?;
+    if !applied.is_empty() {
+        info!("database migrated: {}", applied.join(", "));
+    }
+    Ok(pool)
+}
+
+/// Connection pool of the database at `DATABASE_URL` as it is
+pub(crate) fn unmigrated_pool() -> Result<DbPool> {
+    dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").map_err(|_| StorageError::MissingUrl)?;
+    build(&database_url, false)
+}
+
+/// Read-only connection pool of the database at `DATABASE_URL` for dry runs, none without one.
+/// As it can't be migrated, its schema must be the current one.
+pub(crate) fn read_only_pool() -> Result<Option<DbPool>> {
+    dotenv().ok();
+
+    let Ok(database_url) = env::var("DATABASE_URL") else { return Ok(None) };
+    let pool = build(&database_url, true)?;
+    pool.get()?.check_schema()?;
+    Ok(Some(pool))
+}
+
+/// Connection pool of a PostgreSQL database for `postgres://` and `postgresql://` urls
+/// and of a SQLite database for any other url or path
+pub(crate) fn build(database_url: &str, read_only: bool) -> Result<DbPool> {
+    let options = ConnectionOptions { read_only };
+    let pool = if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        #[cfg(feature = "postgres")]
+        {
+            DbPool::Postgres(connect(database_url, database_url, options)?)
+        }
+        #[cfg(not(feature = "postgres"))]
+        {
+            return Err(StorageError::Unsupported { url: redacted(database_url) });
+        }
+    } else if read_only {
+        // sqlite uri opening the database read-only, which also fails instead of creating a missing one
+        let uri = match database_url.strip_prefix("file:") {
+            Some(uri) if uri.contains('?') => format!("file:{uri}&mode=ro"),
+            Some(uri) => format!("file:{uri}?mode=ro"),
+            None => format!("file:{database_url}?mode=ro"),
+        };
+        DbPool::Sqlite(connect(database_url, &uri, options)?)
+    } else {
+        DbPool::Sqlite(connect(database_url, database_url, options)?)
+    };
+    info!("database {} connected{}", redacted(database_url), if read_only { " read-only" } else { "" });
+    Ok(pool)
+}
+
+fn connect<C>(database_url: &str, uri: &str, options: ConnectionOptions) -> Result<Pool<ConnectionManager<C>>>
+where
+    C: R2D2Connection + 'static,
+    ConnectionOptions: CustomizeConnection<C, diesel::r2d2::Error>,
+{
+    Pool::builder()
+        .max_size(POOL_SIZE)
+        .connection_customizer(Box::new(options))
+        .build(ConnectionManager::new(uri))
+        .map_err(|source| StorageError::Connect { url: redacted(database_url), source })
+}
+
+/// Database url without its password, to be logged
+fn redacted(database_url: &str) -> String {
+    match Url::parse(database_url) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("***"));
+            url.to_string()
+        }
+        _ => database_url.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,396 @@
+use super::*;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_native_tls::native_tls;
+
+/// Path of a certificate of `tests/certs`
+fn cert(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/certs").join(name)
+}
+
+fn settings(proxy: &Url) -> DownloadSettings {
+    DownloadSettings {
+        retries: 0,
+        robots_txt: false,
+        host_interval: Duration::ZERO,
+        proxy: Some(proxy.clone()),
+        ..DownloadSettings::default()
+    }
+}
+
+/// Stand-in of a proxy answering every request itself, like a tls inspecting one also tunneled
+/// requests using the `refuel.test` certificate. Sends the head of every request it answers.
+async fn stand_in() -> (Url, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://user:secret@{}", listener.local_addr().unwrap())).unwrap();
+    let identity = native_tls::Identity::from_pkcs8(&fs::read(cert("server.pem")).unwrap(), &fs::read(cert("server.key")).unwrap()).unwrap();
+    let acceptor = tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+    let (heads, received) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            let heads = heads.clone();
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                let head = read_head(&mut stream).await;
+                if head.starts_with("CONNECT ") {
+                    stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+                    // a client not trusting the certificate fails the handshake
+                    if let Ok(stream) = acceptor.accept(stream.into_inner()).await {
+                        let mut stream = BufReader::new(stream);
+                        let head = read_head(&mut stream).await;
+                        respond(&mut stream, head, &heads).await;
+                    }
+                } else {
+                    respond(&mut stream, head, &heads).await;
+                }
+            });
+        }
+    });
+    (url, received)
+}
+
+/// Stand-in of a proxy like [`stand_in`], whose tunneled server only answers clients authenticating
+/// with a certificate of the test CA. Sends the common name of the certificate of every request it answers.
+fn client_auth_stand_in() -> (Url, mpsc::UnboundedReceiver<String>) {
+    use openssl::nid::Nid;
+    use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key_file(cert("server.key"), SslFiletype::PEM).unwrap();
+    acceptor.set_certificate_chain_file(cert("server.pem")).unwrap();
+    acceptor.set_ca_file(cert("ca.pem")).unwrap();
+    acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    let acceptor = Arc::new(acceptor.build());
+    let (names, received) = mpsc::unbounded_channel();
+
+    // blocking, as openssl streams are, so on threads of their own
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let (mut stream, acceptor, names) = (stream.unwrap(), acceptor.clone(), names.clone());
+            std::thread::spawn(move || {
+                let read_head = |stream: &mut dyn Read| {
+                    let mut head = Vec::new();
+                    let mut byte = [0];
+                    while !head.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap_or(0) > 0 {
+                        head.push(byte[0]);
+                    }
+                };
+                read_head(&mut stream);
+                stream.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").unwrap();
+                // a client without a certificate fails the handshake
+                let Ok(mut stream) = acceptor.accept(stream) else { return };
+                read_head(&mut stream);
+                let name = stream.ssl().peer_certificate()
+                    .and_then(|certificate| certificate.subject_name().entries_by_nid(Nid::COMMONNAME).next()
+                        .and_then(|name| name.data().as_utf8().ok().map(|name| name.to_string())))
+                    .unwrap_or_default();
+                let _ = names.send(name);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok");
+                let _ = stream.shutdown();
+            });
+        }
+    });
+    (url, received)
+}
+
+async fn read_head<S: AsyncRead + Unpin>(stream: &mut BufReader<S>) -> String {
+    let mut head = String::new();
+    while stream.read_line(&mut head).await.unwrap_or(0) > 2 {}
+    head
+}
+
+async fn respond<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut BufReader<S>, head: String, heads: &mpsc::UnboundedSender<String>) {
+    heads.send(head).unwrap();
+    stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok").await.unwrap();
+    stream.shutdown().await.unwrap();
+}
+
+/// Stand-in of a server answering each request with the response `respond` returns for its head.
+/// Sends the head of every request with the time it arrived.
+async fn server<F>(respond: F) -> (Url, mpsc::UnboundedReceiver<(Instant, String)>)
+where
+    F: Fn(&str) -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    let respond = Arc::new(respond);
+    let (heads, received) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (respond, heads) = (respond.clone(), heads.clone());
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                let head = read_head(&mut stream).await;
+                let response = respond(&head);
+                let _ = heads.send((Instant::now(), head));
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            });
+        }
+    });
+    (url, received)
+}
+
+/// Response with the given status line, extra headers and body
+fn response(status: &str, headers: &str, body: &str) -> String {
+    format!("HTTP/1.1 {status}\r\n{headers}content-length: {}\r\nconnection: close\r\n\r\n{body}", body.len())
+}
+
+/// Settings requesting the stand-in server directly, without the proxy of the environment
+fn direct() -> DownloadSettings {
+    DownloadSettings {
+        no_proxy: Some("127.0.0.1".to_owned()),
+        ..settings(&Url::parse("http://127.0.0.1:9").unwrap())
+    }
+}
+
+#[tokio::test]
+async fn requests_go_through_the_proxy() {
+    let (proxy, mut heads) = stand_in().await;
+    let downloader = Downloader::new(&settings(&proxy)).unwrap();
+    let document = downloader.download(&Url::parse("http://refuel.test/?fuel=e5").unwrap()).await.unwrap();
+    assert_eq!(document, "ok");
+
+    let head = heads.recv().await.unwrap().to_lowercase();
+    assert!(head.starts_with("get http://refuel.test/?fuel=e5 http/1.1\r\n"), "{head}");
+    // user:secret
+    assert!(head.contains("proxy-authorization: basic dxnlcjpzzwnyzxq=\r\n"), "{head}");
+}
+
+#[tokio::test]
+async fn tls_through_the_proxy_trusts_the_root_certificates() {
+    let (proxy, mut heads) = stand_in().await;
+    let url = Url::parse("https://refuel.test/?fuel=e5").unwrap();
+
+    let untrusting = Downloader::new(&settings(&proxy)).unwrap();
+    assert!(matches!(untrusting.download(&url).await, Err(DownloadError::Request { .. })));
+
+    let trusting = Downloader::new(&DownloadSettings {
+        root_certificates: vec![cert("ca.pem")],
+        ..settings(&proxy)
+    }).unwrap();
+    assert_eq!(trusting.download(&url).await.unwrap(), "ok");
+    assert!(heads.recv().await.unwrap().starts_with("GET /?fuel=e5 HTTP/1.1\r\n"));
+}
+
+#[tokio::test]
+async fn hosts_excluded_from_the_proxy_are_requested_directly() {
+    let (proxy, mut proxied) = stand_in().await;
+    let (server, mut direct) = stand_in().await;
+    let downloader = Downloader::new(&DownloadSettings {
+        no_proxy: Some("127.0.0.1".to_owned()),
+        ..settings(&proxy)
+    }).unwrap();
+    let mut url = server.clone();
+    url.set_username("").unwrap();
+    url.set_password(None).unwrap();
+    assert_eq!(downloader.download(&url).await.unwrap(), "ok");
+
+    // a proxied request would name the whole url
+    assert!(direct.recv().await.unwrap().starts_with("GET / HTTP/1.1\r\n"));
+    assert!(proxied.try_recv().is_err());
+}
+
+#[test]
+fn certificate_files_are_checked() {
+    let proxy = Url::parse("http://127.0.0.1:3128").unwrap();
+    let not_a_certificate = DownloadSettings { root_certificates: vec![cert("client.key")], ..settings(&proxy) };
+    assert!(matches!(Downloader::new(&not_a_certificate), Err(DownloadError::Certificate { .. })));
+
+    let missing = DownloadSettings { root_certificates: vec![cert("missing.pem")], ..settings(&proxy) };
+    assert!(matches!(Downloader::new(&missing), Err(DownloadError::CertificateIo { .. })));
+
+    let swapped = DownloadSettings {
+        client_certificate: Some(cert("client.key")),
+        client_key: Some(cert("client.pem")),
+        ..settings(&proxy)
+    };
+    assert!(matches!(Downloader::new(&swapped), Err(DownloadError::Certificate { .. })));
+}
+
+#[tokio::test]
+async fn client_certificates_authenticate_requests() {
+    let (proxy, mut names) = client_auth_stand_in();
+    let url = Url::parse("https://refuel.test/?fuel=e5").unwrap();
+    let trusting = DownloadSettings { root_certificates: vec![cert("ca.pem")], ..settings(&proxy) };
+
+    let anonymous = Downloader::new(&trusting).unwrap();
+    let err = anonymous.download(&url).await.unwrap_err();
+    assert!(matches!(err, DownloadError::Request { .. }), "{err}");
+
+    let authenticated = Downloader::new(&DownloadSettings {
+        client_certificate: Some(cert("client.pem")),
+        client_key: Some(cert("client.key")),
+        ..trusting
+    }).unwrap();
+    assert_eq!(authenticated.download(&url).await.unwrap(), "ok");
+    assert_eq!(names.recv().await.unwrap(), "refuel-server");
+    assert!(names.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried() {
+    let requests = Arc::new(Mutex::new(0));
+    let counted = requests.clone();
+    let (url, _) = server(move |_| {
+        *lock(&counted) += 1;
+        match *lock(&counted) {
+            1 => response("503 Service Unavailable", "", "Wartungsarbeiten"),
+            _ => response("200 OK", "", "ok"),
+        }
+    }).await;
+
+    let failing = Downloader::new(&direct()).unwrap();
+    let err = failing.download(&url).await.unwrap_err();
+    // the maintenance page is never returned as document
+    assert!(matches!(err, DownloadError::Status { status: StatusCode::SERVICE_UNAVAILABLE, .. }), "{err}");
+    assert!(err.is_transient());
+
+    let retrying = Downloader::new(&DownloadSettings { retries: 1, backoff: Duration::from_millis(10), ..direct() }).unwrap();
+    assert_eq!(retrying.download(&url).await.unwrap(), "ok");
+    *lock(&requests) = 0;
+    assert_eq!(retrying.download(&url).await.unwrap(), "ok");
+    assert_eq!(*lock(&requests), 2);
+}
+
+#[tokio::test]
+async fn permanent_failures_are_not_retried() {
+    let (url, mut heads) = server(|_| response("404 Not Found", "", "nicht gefunden")).await;
+    let downloader = Downloader::new(&DownloadSettings { retries: 3, backoff: Duration::from_millis(10), ..direct() }).unwrap();
+    let err = downloader.download(&url).await.unwrap_err();
+    assert!(matches!(err, DownloadError::Status { status: StatusCode::NOT_FOUND, retry_after: None, .. }), "{err}");
+    assert!(!err.is_transient());
+    heads.recv().await.unwrap();
+    assert!(heads.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn retries_wait_as_long_as_the_server_asks() {
+    let requests = Arc::new(Mutex::new(0));
+    let (url, mut heads) = server(move |_| {
+        *lock(&requests) += 1;
+        match *lock(&requests) {
+            1 => response("429 Too Many Requests", "retry-after: 1\r\n", ""),
+            _ => response("200 OK", "", "ok"),
+        }
+    }).await;
+    let downloader = Downloader::new(&DownloadSettings { retries: 1, backoff: Duration::from_millis(10), ..direct() }).unwrap();
+    assert_eq!(downloader.download(&url).await.unwrap(), "ok");
+
+    let (first, _) = heads.recv().await.unwrap();
+    let (second, _) = heads.recv().await.unwrap();
+    assert!(second - first >= Duration::from_secs(1), "retried after {:?}", second - first);
+}
+
+#[tokio::test]
+async fn retry_after_is_bounded_by_the_max_backoff() {
+    let requests = Arc::new(Mutex::new(0));
+    let (url, mut heads) = server(move |_| {
+        *lock(&requests) += 1;
+        match *lock(&requests) {
+            1 => response("503 Service Unavailable", "retry-after: 3600\r\n", ""),
+            _ => response("200 OK", "", "ok"),
+        }
+    }).await;
+    let downloader = Downloader::new(&DownloadSettings {
+        retries: 1,
+        max_backoff: Duration::from_millis(50),
+        ..direct()
+    }).unwrap();
+    assert_eq!(downloader.download(&url).await.unwrap(), "ok");
+
+    let (first, _) = heads.recv().await.unwrap();
+    let (second, _) = heads.recv().await.unwrap();
+    assert!(second - first < Duration::from_secs(1), "retried after {:?}", second - first);
+}
+
+#[tokio::test]
+async fn unmodified_documents_are_skipped_until_forgotten() {
+    let (url, mut heads) = server(|head| {
+        if head.contains("if-none-match: \"v1\"\r\n") {
+            response("304 Not Modified", "", "")
+        } else {
+            response("200 OK", "etag: \"v1\"\r\nlast-modified: Sun, 18 Oct 2026 09:00:00 GMT\r\n", "ok")
+        }
+    }).await;
+    let downloader = Downloader::new(&direct()).unwrap();
+
+    assert_eq!(downloader.download_if_modified(&url).await.unwrap().as_deref(), Some("ok"));
+    assert_eq!(downloader.download_if_modified(&url).await.unwrap(), None);
+    let (_, first) = heads.recv().await.unwrap();
+    let (_, second) = heads.recv().await.unwrap();
+    assert!(!first.contains("if-none-match"), "{first}");
+    assert!(second.contains("if-modified-since: Sun, 18 Oct 2026 09:00:00 GMT\r\n"), "{second}");
+
+    // a plain download neither sends nor changes the validators
+    assert_eq!(downloader.download(&url).await.unwrap(), "ok");
+    assert_eq!(downloader.download_if_modified(&url).await.unwrap(), None);
+
+    downloader.forget(&url);
+    assert_eq!(downloader.download_if_modified(&url).await.unwrap().as_deref(), Some("ok"));
+}
+
+#[tokio::test]
+async fn paths_disallowed_by_robots_txt_are_not_requested() {
+    let (url, mut heads) = server(|head| {
+        if head.starts_with("GET /robots.txt ") {
+            response("200 OK", "", "User-agent: refuel-server\nDisallow: /private\n")
+        } else {
+            response("200 OK", "", "ok")
+        }
+    }).await;
+    let downloader = Downloader::new(&DownloadSettings { robots_txt: true, ..direct() }).unwrap();
+
+    let private = url.join("/private/prices").unwrap();
+    let err = downloader.download(&private).await.unwrap_err();
+    assert!(matches!(err, DownloadError::Disallowed { .. }), "{err}");
+    assert!(!err.is_transient());
+    assert_eq!(downloader.download(&url.join("/prices").unwrap()).await.unwrap(), "ok");
+
+    // the robots.txt is downloaded once, the disallowed path never
+    let (_, robots_txt) = heads.recv().await.unwrap();
+    let (_, allowed) = heads.recv().await.unwrap();
+    assert!(robots_txt.starts_with("GET /robots.txt "), "{robots_txt}");
+    assert!(allowed.starts_with("GET /prices "), "{allowed}");
+    assert!(heads.try_recv().is_err());
+
+    let ignoring = Downloader::new(&direct()).unwrap();
+    assert_eq!(ignoring.download(&private).await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn requests_to_one_host_are_spaced() {
+    let (url, mut heads) = server(|_| response("200 OK", "", "ok")).await;
+    let (other, _) = server(|_| response("200 OK", "", "ok")).await;
+    let interval = Duration::from_millis(300);
+    let downloader = Downloader::new(&DownloadSettings { host_interval: interval, ..direct() }).unwrap();
+
+    let started = Instant::now();
+    let downloads = (0..3).map(|_| downloader.download(&url));
+    for document in futures::future::join_all(downloads).await {
+        assert_eq!(document.unwrap(), "ok");
+    }
+    // the server receives a request later than it is sent, by the time connecting takes,
+    // so only the last one is known not to be sent before its slot
+    let mut last = started;
+    for _ in 0..3 {
+        last = last.max(heads.recv().await.unwrap().0);
+    }
+    assert!(last - started >= interval * 2, "{:?}", last - started);
+
+    // another host, here another port, is not held up by the slot reserved next
+    let started = Instant::now();
+    downloader.download(&url).await.unwrap();
+    downloader.download(&other).await.unwrap();
+    assert!(started.elapsed() < interval * 2, "{:?}", started.elapsed());
+}
@@ -1,10 +1,16 @@
+use crate::fuel::FuelType;
+
 use thiserror::Error;
 
 use regex::Regex;
 use scraper::Selector;
+use std::fmt;
+use std::io;
 use std::num;
+use std::path::PathBuf;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum ParseError {
     #[error("invalid price detected\nregex: {regex}\nhtml:\n{html}\n")]
     InvalidPriceError { html: String, regex: Regex },
@@ -14,6 +20,158 @@ pub enum ParseError {
     HtmlSelectError { html: String, selector: Selector },
     #[error("regex mismatch error\nregex: {regex}\nhtml:\n{html}\n")]
     RegexMismatchError { html: String, regex: Regex },
+    #[error("price list not found\nselector: {selector:?}\n")]
+    ListNotFoundError { selector: Selector },
+    #[error("price out of range\nhtml:\n{html}\n")]
+    PriceRangeError { html: String },
+    #[error("no {fuel} price for {name}")]
+    MissingPriceError { name: String, fuel: FuelType },
+    #[error("unknown currency: {0}")]
+    UnknownCurrencyError(String),
+    #[error("invalid date or time: {0}")]
+    InvalidDateError(String),
+    #[error("local time {datetime} does not exist in time zone {timezone}")]
+    NonexistentLocalTimeError { datetime: chrono::NaiveDateTime, timezone: chrono_tz::Tz },
     #[error("string to number convertion error")]
     ParseIntError(#[from] num::ParseIntError),
+    #[error("json document invalid")]
+    JsonError(#[from] serde_json::Error),
+    #[error("api reported error: {0}")]
+    ApiError(String),
+    #[error("site profile {path:?} not readable")]
+    ProfileIoError { path: PathBuf, source: io::Error },
+    #[error("site profile {path:?} invalid")]
+    ProfileTomlError { path: PathBuf, source: toml::de::Error },
+    #[error("invalid {name} selector in site profile: {selector}\n{message}")]
+    InvalidSelectorError { name: &'static str, selector: String, message: String },
+    #[error("invalid {name} regex in site profile")]
+    InvalidRegexError { name: &'static str, source: regex::Error },
+    #[error("{name} regex in site profile lacks capture group {group}\nregex: {regex}")]
+    MissingCaptureGroupError { name: &'static str, regex: Regex, group: &'static str },
+}
+
+impl ParseError {
+    /// Whether the item is fine but does not show a price right now, e.g. of a closed refuel station
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self, ParseError::InvalidPriceError { .. }
+            | ParseError::InvalidUpdatedError { .. }
+            | ParseError::MissingPriceError { .. })
+    }
+
+    /// Short name of the kind of error, to group skipped items by
+    pub fn reason(&self) -> &'static str {
+        match self {
+            ParseError::InvalidPriceError { .. } => "invalid-price",
+            ParseError::InvalidUpdatedError { .. } => "invalid-updated",
+            ParseError::HtmlSelectError { .. } => "html-select",
+            ParseError::RegexMismatchError { .. } => "regex-mismatch",
+            ParseError::ListNotFoundError { .. } => "list-not-found",
+            ParseError::PriceRangeError { .. } => "price-range",
+            ParseError::MissingPriceError { .. } => "missing-price",
+            ParseError::UnknownCurrencyError(_) => "unknown-currency",
+            ParseError::InvalidDateError(_) => "invalid-date",
+            ParseError::NonexistentLocalTimeError { .. } => "nonexistent-local-time",
+            ParseError::ParseIntError(_) => "parse-int",
+            ParseError::JsonError(_) => "json",
+            ParseError::ApiError(_) => "api",
+            ParseError::ProfileIoError { .. }
+            | ParseError::ProfileTomlError { .. }
+            | ParseError::InvalidSelectorError { .. }
+            | ParseError::InvalidRegexError { .. }
+            | ParseError::MissingCaptureGroupError { .. } => "profile",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DownloadError {
+    #[error("http client not created")]
+    Client(#[source] reqwest::Error),
+    #[error("request to {url} failed")]
+    Request { url: url::Url, source: reqwest::Error },
+    #[error("proxy {url} invalid")]
+    Proxy { url: url::Url, source: reqwest::Error },
+    #[error("certificate file {path:?} not readable")]
+    CertificateIo { path: PathBuf, source: io::Error },
+    #[error("certificate file {path:?} invalid")]
+    Certificate { path: PathBuf, source: reqwest::Error },
+    #[error("{url} disallowed by robots.txt")]
+    Disallowed { url: url::Url },
+    #[error("{url} responded with status {status}")]
+    Status { url: url::Url, status: reqwest::StatusCode, retry_after: Option<std::time::Duration> },
+}
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("archive file {path:?} not accessible")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("archive index {path:?} invalid in line {line}")]
+    Index { path: PathBuf, line: usize, source: serde_json::Error },
+    #[error("archived document {hash} corrupt")]
+    Corrupt { hash: String },
+}
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("pattern {pattern:?} invalid")]
+    Pattern { pattern: String, source: glob::PatternError },
+    #[error("saved document {path:?} not accessible")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("capture time {datetime} of {path:?} does not exist in {timezone}")]
+    NonexistentCaptureTime { path: PathBuf, datetime: chrono::NaiveDateTime, timezone: chrono_tz::Tz },
+}
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("DATABASE_URL must be set")]
+    MissingUrl,
+    #[cfg(not(feature = "postgres"))]
+    #[error("database {url} needs a build with the `postgres` feature")]
+    Unsupported { url: String },
+    #[error("database {url} not connected")]
+    Connect { url: String, source: diesel::r2d2::PoolError },
+    #[error("database connection not available")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("database query failed")]
+    Query(#[from] diesel::result::Error),
+    #[error("database not migrated")]
+    Migration(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("database schema lacks the migrations {migrations}, run `refuel-server migrate`")]
+    PendingMigrations { migrations: String },
+    #[error("database schema has the migrations {migrations} unknown to this refuel-server, which is older than the one that migrated it")]
+    NewerSchema { migrations: String },
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("config file {path:?} not readable")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("config file {path:?} invalid")]
+    Toml { path: PathBuf, source: toml::de::Error },
+    #[error("site profile of target {url} invalid")]
+    Profile { url: url::Url, source: Box<ParseError> },
+    #[error("config invalid: {0}")]
+    Invalid(String),
+}
+
+/// Displays an error followed by all its sources, as their messages leave out the cause,
+/// e.g. `request to http://localhost:8080/ failed: error sending request: connection refused`.
+/// Sources some message already includes, like hyper's do, are left out.
+pub(crate) struct ErrorChain<'a>(pub &'a dyn std::error::Error);
+
+impl fmt::Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut message = self.0.to_string();
+        write!(f, "{message}")?;
+        let mut source = self.0.source();
+        while let Some(err) = source {
+            let next = err.to_string();
+            if !message.contains(&next) {
+                write!(f, ": {next}")?;
+                message = next;
+            }
+            source = err.source();
+        }
+        Ok(())
+    }
 }
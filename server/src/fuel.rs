@@ -0,0 +1,90 @@
+use clap::ValueEnum;
+use diesel::backend::Backend;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::Sqlite;
+#[cfg(feature = "postgres")]
+use diesel::pg::Pg;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, ValueEnum, Deserialize, AsExpression, FromSqlRow)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub(crate) enum FuelType {
+    /// Diesel
+    Diesel,
+    /// Super E5
+    E5,
+    /// Super E10
+    E10,
+    /// Liquefied petroleum gas, autogas
+    Lpg,
+    /// Compressed natural gas
+    Cng,
+}
+
+impl FuelType {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            FuelType::Diesel => "diesel",
+            FuelType::E5 => "e5",
+            FuelType::E10 => "e10",
+            FuelType::Lpg => "lpg",
+            FuelType::Cng => "cng",
+        }
+    }
+}
+
+impl fmt::Display for FuelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FuelType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "diesel" => Ok(FuelType::Diesel),
+            "e5" => Ok(FuelType::E5),
+            "e10" => Ok(FuelType::E10),
+            "lpg" => Ok(FuelType::Lpg),
+            "cng" => Ok(FuelType::Cng),
+            _ => Err(format!("unknown fuel type: {s}")),
+        }
+    }
+}
+
+impl ToSql<Text, Sqlite> for FuelType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.as_str());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Sqlite> for FuelType {
+    fn from_sql(bytes: <Sqlite as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let fuel = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(fuel.parse()?)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl ToSql<Text, Pg> for FuelType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl FromSql<Text, Pg> for FuelType {
+    fn from_sql(bytes: <Pg as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        let fuel = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(fuel.parse()?)
+    }
+}
@@ -1,19 +1,33 @@
-mod helloworld;
+mod fuel_prices;
 
-use self::helloworld::hello_world::greeter_server::GreeterServer;
-use self::helloworld::MyGreeter;
+use self::fuel_prices::refuel::fuel_prices_server::FuelPricesServer;
+use self::fuel_prices::FuelPricesService;
 
+use crate::db::DbPool;
+use crate::wait_for_shutdown;
+use crate::models::PriceChange;
+
+use std::net::SocketAddr;
+use tokio::sync::{broadcast, watch};
 use tonic::transport::{Error, Server};
 
 use tracing::info;
 
-pub(crate) async fn service() -> Result<(), Error> {
-    let addr = "[::1]:50051".parse().unwrap();
+/// Sending half of the channel which feeds newly saved price changes to watchers
+pub(crate) type PriceChangeSender = broadcast::Sender<PriceChange>;
+
+pub(crate) fn price_change_channel() -> PriceChangeSender {
+    let (sender, _) = broadcast::channel(256);
+    sender
+}
 
-    let greeter = MyGreeter::default();
+/// Serves until the shutdown is signaled, which also ends the streams of all watchers
+pub(crate) async fn service(addr: SocketAddr, pool: DbPool, changes: PriceChangeSender,
+                            shutdown: watch::Receiver<bool>) -> Result<(), Error> {
+    let fuel_prices = FuelPricesService::new(pool, changes, shutdown.clone());
     let service = Server::builder()
-        .add_service(GreeterServer::new(greeter))
-        .serve(addr);
-    info!("GreeterServer listening on {}", addr);
+        .add_service(FuelPricesServer::new(fuel_prices))
+        .serve_with_shutdown(addr, wait_for_shutdown(shutdown));
+    info!("FuelPricesServer listening on {}", addr);
     service.await
 }
@@ -0,0 +1,214 @@
+#![allow(clippy::result_large_err)]
+
+use super::PriceChangeSender;
+use crate::db::{DbConnection, DbPool};
+use crate::error::{ErrorChain, StorageError};
+use crate::fuel::FuelType;
+use crate::models::{PriceChange, RefuelStation};
+use crate::wait_for_shutdown;
+
+use tonic::{Request, Response, Status};
+
+use refuel::fuel_prices_server::FuelPrices;
+use refuel::{
+    GetCurrentPricesReply, GetCurrentPricesRequest, GetPriceHistoryReply, GetPriceHistoryRequest,
+    ListStationsReply, ListStationsRequest, WatchPriceChangesRequest,
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use diesel::prelude::*;
+use std::pin::Pin;
+use tokio::sync::watch;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use tracing::{debug, error, warn};
+
+pub mod refuel {
+    tonic::include_proto!("refuel");
+}
+
+pub struct FuelPricesService {
+    pool: DbPool,
+    changes: PriceChangeSender,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl FuelPricesService {
+    pub fn new(pool: DbPool, changes: PriceChangeSender, shutdown: watch::Receiver<bool>) -> Self {
+        Self { pool, changes, shutdown }
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, Status>
+    where
+        F: FnOnce(&mut DbConnection) -> QueryResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.pool.run(|conn| f(conn).map_err(StorageError::from)).await.map_err(|err| match err {
+            StorageError::Pool(err) => {
+                error!("database connection not available: {}", ErrorChain(&err));
+                Status::unavailable("database connection not available")
+            }
+            err => {
+                error!("{}", ErrorChain(&err));
+                Status::internal("database query failed")
+            }
+        })
+    }
+}
+
+type PriceChangeStream = Pin<Box<dyn Stream<Item = Result<refuel::PriceChange, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl FuelPrices for FuelPricesService {
+    type WatchPriceChangesStream = PriceChangeStream;
+
+    async fn list_stations(
+        &self,
+        request: Request<ListStationsRequest>,
+    ) -> Result<Response<ListStationsReply>, Status> {
+        debug!("Got a request from {:?}", request.remote_addr());
+
+        let stations = self.with_conn(RefuelStation::load_all).await?;
+        let reply = ListStationsReply {
+            stations: stations.into_iter().map(Into::into).collect(),
+        };
+        Ok(Response::new(reply))
+    }
+
+    async fn get_current_prices(
+        &self,
+        request: Request<GetCurrentPricesRequest>,
+    ) -> Result<Response<GetCurrentPricesReply>, Status> {
+        debug!("Got a request from {:?}", request.remote_addr());
+
+        let fuels = to_fuels(&request.into_inner().fuels)?;
+        let prices = self.with_conn(move |conn| PriceChange::load_current(conn, &fuels)).await?;
+        let reply = GetCurrentPricesReply {
+            prices: prices.into_iter().map(Into::into).collect(),
+        };
+        Ok(Response::new(reply))
+    }
+
+    async fn get_price_history(
+        &self,
+        request: Request<GetPriceHistoryRequest>,
+    ) -> Result<Response<GetPriceHistoryReply>, Status> {
+        debug!("Got a request from {:?}", request.remote_addr());
+
+        let request = request.into_inner();
+        let station = request.station.ok_or_else(|| Status::invalid_argument("station missing"))?;
+        let from = request.from.map(to_datetime).transpose()?;
+        let to = request.to.map(to_datetime).transpose()?;
+        let fuels = to_fuels(&request.fuels)?;
+
+        let station = self.with_conn(move |conn| {
+            if station.id != 0 {
+                RefuelStation::load(conn, station.id)
+            } else {
+                RefuelStation::find(conn, &station.name, &station.addr)
+            }
+        }).await?;
+        let station = station.ok_or_else(|| Status::not_found("station not found"))?;
+
+        let prices = self.with_conn(move |conn| PriceChange::load_history(conn, &station, &fuels, from, to)).await?;
+        let reply = GetPriceHistoryReply {
+            prices: prices.into_iter().map(Into::into).collect(),
+        };
+        Ok(Response::new(reply))
+    }
+
+    async fn watch_price_changes(
+        &self,
+        request: Request<WatchPriceChangesRequest>,
+    ) -> Result<Response<Self::WatchPriceChangesStream>, Status> {
+        debug!("Got a request from {:?}", request.remote_addr());
+
+        let filter = request.into_inner();
+        let fuels = to_fuels(&filter.fuels)?;
+        let stream = BroadcastStream::new(self.changes.subscribe())
+            .filter_map(move |change| match change {
+                Ok(change) if filter.matches(&change, &fuels) => Some(Ok(change.into())),
+                Ok(_) => None,
+                Err(err) => {
+                    warn!("watcher fell behind: {err}");
+                    None
+                }
+            });
+        // the channel outlives the server, so the stream would keep a graceful shutdown waiting
+        let stream = futures::StreamExt::take_until(stream, wait_for_shutdown(self.shutdown.clone()));
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+impl WatchPriceChangesRequest {
+    fn matches(&self, change: &PriceChange, fuels: &[FuelType]) -> bool {
+        let name = self.name.as_ref().is_none_or(|name| change.station.name.contains(name.as_str()));
+        let addr = self.addr.as_ref().is_none_or(|addr| change.station.addr.contains(addr.as_str()));
+        let fuel = fuels.is_empty() || fuels.contains(&change.fuel);
+        name && addr && fuel
+    }
+}
+
+fn to_fuels(fuels: &[i32]) -> Result<Vec<FuelType>, Status> {
+    fuels.iter()
+        .map(|fuel| match refuel::FuelType::from_i32(*fuel) {
+            Some(refuel::FuelType::Diesel) => Ok(FuelType::Diesel),
+            Some(refuel::FuelType::E5) => Ok(FuelType::E5),
+            Some(refuel::FuelType::E10) => Ok(FuelType::E10),
+            Some(refuel::FuelType::Lpg) => Ok(FuelType::Lpg),
+            Some(refuel::FuelType::Cng) => Ok(FuelType::Cng),
+            Some(refuel::FuelType::Unspecified) | None => Err(Status::invalid_argument("invalid fuel type")),
+        })
+        .collect()
+}
+
+fn to_timestamp(datetime: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: datetime.timestamp(),
+        nanos: datetime.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn to_datetime(timestamp: prost_types::Timestamp) -> Result<DateTime<Utc>, Status> {
+    let nanos = u32::try_from(timestamp.nanos).map_err(|_| Status::invalid_argument("invalid timestamp"))?;
+    Utc.timestamp_opt(timestamp.seconds, nanos).single().ok_or_else(|| Status::invalid_argument("invalid timestamp"))
+}
+
+impl From<RefuelStation> for refuel::Station {
+    fn from(src: RefuelStation) -> Self {
+        Self {
+            name: src.name,
+            addr: src.addr,
+            id: src.id,
+        }
+    }
+}
+
+impl From<PriceChange> for refuel::PriceChange {
+    fn from(src: PriceChange) -> Self {
+        Self {
+            station: Some(src.station.into()),
+            fuel: refuel::FuelType::from(src.fuel).into(),
+            updated: Some(to_timestamp(src.updated)),
+            // prices are positive, see RefuelStationPriceChange
+            price: src.price.unsigned_abs(),
+            currency: src.currency,
+        }
+    }
+}
+
+impl From<FuelType> for refuel::FuelType {
+    fn from(src: FuelType) -> Self {
+        match src {
+            FuelType::Diesel => Self::Diesel,
+            FuelType::E5 => Self::E5,
+            FuelType::E10 => Self::E10,
+            FuelType::Lpg => Self::Lpg,
+            FuelType::Cng => Self::Cng,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,159 @@
+use super::*;
+use crate::db::build;
+use crate::grpc::price_change_channel;
+use crate::testing::{price_change, scratch};
+use std::fs;
+use std::path::Path;
+use tonic::Code;
+
+fn timestamp(minute: u32) -> prost_types::Timestamp {
+    to_timestamp(Utc.with_ymd_and_hms(2026, 10, 18, 9, minute, 0).unwrap())
+}
+
+/// Service of a migrated SQLite database in `dir` holding prices of two stations
+fn service(dir: &Path) -> FuelPricesService {
+    let pool = build(dir.join("refuel.db").to_str().unwrap(), false).unwrap();
+    let conn = &mut pool.get().unwrap();
+    conn.migrate().unwrap();
+    for change in [
+        price_change("MyESSO", FuelType::E5, 0, 1759),
+        price_change("MyESSO", FuelType::E5, 30, 1749),
+        price_change("MyESSO", FuelType::Diesel, 10, 1659),
+        price_change("MyJET", FuelType::E5, 20, 1769),
+    ] {
+        change.save(conn).unwrap();
+    }
+    FuelPricesService::new(pool, price_change_channel(), watch::channel(false).1)
+}
+
+fn station(name: &str) -> refuel::Station {
+    refuel::Station { name: name.to_owned(), addr: format!("{name} street 1, Berlin"), id: 0 }
+}
+
+fn prices(prices: &[refuel::PriceChange]) -> Vec<(String, refuel::FuelType, i64, u64)> {
+    prices.iter()
+        .map(|price| {
+            let station = price.station.as_ref().unwrap();
+            (station.name.clone(), price.fuel(), price.updated.as_ref().unwrap().seconds, price.price)
+        })
+        .collect()
+}
+
+fn history(station: refuel::Station) -> GetPriceHistoryRequest {
+    GetPriceHistoryRequest { station: Some(station), from: None, to: None, fuels: vec![] }
+}
+
+#[tokio::test]
+async fn stations_are_listed_by_name() {
+    let dir = scratch("grpc-stations");
+    let service = service(&dir);
+    let stations = service.list_stations(Request::new(ListStationsRequest {})).await.unwrap().into_inner().stations;
+    let names: Vec<_> = stations.iter().map(|station| (station.name.as_str(), station.addr.as_str())).collect();
+    assert_eq!(names, vec![("MyESSO", "MyESSO street 1, Berlin"), ("MyJET", "MyJET street 1, Berlin")]);
+    assert!(stations.iter().all(|station| station.id != 0));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn current_prices_are_the_latest_per_fuel() {
+    let dir = scratch("grpc-current-prices");
+    let service = service(&dir);
+    let current = |fuels: Vec<refuel::FuelType>| {
+        let request = GetCurrentPricesRequest { fuels: fuels.into_iter().map(Into::into).collect() };
+        service.get_current_prices(Request::new(request))
+    };
+
+    let all = current(vec![]).await.unwrap().into_inner().prices;
+    assert_eq!(prices(&all), vec![
+        ("MyESSO".to_owned(), refuel::FuelType::Diesel, timestamp(10).seconds, 1659),
+        ("MyESSO".to_owned(), refuel::FuelType::E5, timestamp(30).seconds, 1749),
+        ("MyJET".to_owned(), refuel::FuelType::E5, timestamp(20).seconds, 1769),
+    ]);
+    assert!(all.iter().all(|price| price.currency == "EUR"));
+    let diesel = current(vec![refuel::FuelType::Diesel]).await.unwrap().into_inner().prices;
+    assert_eq!(prices(&diesel), vec![("MyESSO".to_owned(), refuel::FuelType::Diesel, timestamp(10).seconds, 1659)]);
+    assert!(current(vec![refuel::FuelType::Lpg]).await.unwrap().into_inner().prices.is_empty());
+    assert_eq!(current(vec![refuel::FuelType::Unspecified]).await.unwrap_err().code(), Code::InvalidArgument);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn price_history_is_limited_to_the_range_and_fuels() {
+    let dir = scratch("grpc-price-history");
+    let service = service(&dir);
+    let load = |request| async { service.get_price_history(Request::new(request)).await.unwrap().into_inner().prices };
+
+    let all = load(history(station("MyESSO"))).await;
+    assert_eq!(prices(&all), vec![
+        ("MyESSO".to_owned(), refuel::FuelType::E5, timestamp(0).seconds, 1759),
+        ("MyESSO".to_owned(), refuel::FuelType::Diesel, timestamp(10).seconds, 1659),
+        ("MyESSO".to_owned(), refuel::FuelType::E5, timestamp(30).seconds, 1749),
+    ]);
+    let id = all[0].station.as_ref().unwrap().id;
+    let by_id = load(history(refuel::Station { id, ..Default::default() })).await;
+    assert_eq!(prices(&by_id), prices(&all));
+
+    let range = GetPriceHistoryRequest {
+        from: Some(timestamp(10)),
+        to: Some(timestamp(29)),
+        ..history(station("MyESSO"))
+    };
+    assert_eq!(prices(&load(range).await), vec![("MyESSO".to_owned(), refuel::FuelType::Diesel, timestamp(10).seconds, 1659)]);
+    let e5 = GetPriceHistoryRequest { fuels: vec![refuel::FuelType::E5.into()], ..history(station("MyESSO")) };
+    assert_eq!(load(e5).await.len(), 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn invalid_price_history_requests_are_rejected() {
+    let dir = scratch("grpc-invalid-history");
+    let service = service(&dir);
+    let code = |request| async { service.get_price_history(Request::new(request)).await.unwrap_err().code() };
+
+    assert_eq!(code(GetPriceHistoryRequest { station: None, ..history(station("MyESSO")) }).await, Code::InvalidArgument);
+    let invalid = prost_types::Timestamp { seconds: 0, nanos: -1 };
+    assert_eq!(code(GetPriceHistoryRequest { from: Some(invalid), ..history(station("MyESSO")) }).await, Code::InvalidArgument);
+    assert_eq!(code(GetPriceHistoryRequest { fuels: vec![42], ..history(station("MyESSO")) }).await, Code::InvalidArgument);
+    assert_eq!(code(history(station("MyAGIP"))).await, Code::NotFound);
+    assert_eq!(code(history(refuel::Station { id: 4242, ..Default::default() })).await, Code::NotFound);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Saved price change of a station as broadcast to watchers
+fn saved(name: &str, fuel: FuelType, minute: u32, price: i64) -> PriceChange {
+    let change = price_change(name, fuel, minute, price);
+    let station = RefuelStation { id: 1, name: change.name, addr: change.addr };
+    PriceChange { station, fuel, updated: change.updated, price, currency: change.currency }
+}
+
+#[tokio::test]
+async fn watchers_get_the_matching_price_changes() {
+    let dir = scratch("grpc-watch");
+    let pool = build(dir.join("refuel.db").to_str().unwrap(), false).unwrap();
+    let changes = price_change_channel();
+    let (shutdown, shutdown_rx) = watch::channel(false);
+    let service = FuelPricesService::new(pool, changes.clone(), shutdown_rx);
+
+    let request = WatchPriceChangesRequest { name: Some("ESSO".to_owned()), addr: None, fuels: vec![refuel::FuelType::E5.into()] };
+    let mut stream = service.watch_price_changes(Request::new(request)).await.unwrap().into_inner();
+    assert!(changes.send(saved("MyJET", FuelType::E5, 0, 1769)).is_ok());
+    assert!(changes.send(saved("MyESSO", FuelType::Diesel, 0, 1659)).is_ok());
+    assert!(changes.send(saved("MyESSO", FuelType::E5, 10, 1749)).is_ok());
+    let change = stream.next().await.unwrap().unwrap();
+    assert_eq!(prices(&[change]), vec![("MyESSO".to_owned(), refuel::FuelType::E5, timestamp(10).seconds, 1749)]);
+
+    // a shutdown ends the stream although the channel stays open
+    shutdown.send(true).unwrap();
+    assert!(tokio::time::timeout(std::time::Duration::from_secs(5), stream.next()).await.unwrap().is_none());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn watchers_of_invalid_fuels_are_rejected() {
+    let dir = scratch("grpc-watch-invalid");
+    let pool = build(dir.join("refuel.db").to_str().unwrap(), false).unwrap();
+    let service = FuelPricesService::new(pool, price_change_channel(), watch::channel(false).1);
+    let request = WatchPriceChangesRequest { name: None, addr: None, fuels: vec![refuel::FuelType::Unspecified.into()] };
+    assert_eq!(service.watch_price_changes(Request::new(request)).await.err().unwrap().code(), Code::InvalidArgument);
+    fs::remove_dir_all(dir).unwrap();
+}
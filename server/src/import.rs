@@ -0,0 +1,59 @@
+use crate::error::ImportError;
+use crate::load::file_modified;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+type Result<T> = std::result::Result<T, ImportError>;
+
+/// Default pattern of capture times in file names like `e5-2023-07-01T12-30-00.html` or `20230701_1230.html`
+pub(crate) const DEFAULT_TIME_PATTERN: &str =
+    r"(?P<y>\d{4})-?(?P<m>\d{2})-?(?P<d>\d{2})[T_ -]?(?P<h>\d{2})[:.-]?(?P<min>\d{2})(?:[:.-]?(?P<s>\d{2}))?";
+
+/// Files of a directory or matching a glob pattern
+pub(crate) fn saved_documents(path: &str) -> Result<Vec<PathBuf>> {
+    let dir = Path::new(path);
+    let files = if dir.is_dir() {
+        let entries = fs::read_dir(dir).map_err(|source| ImportError::Io { path: dir.to_owned(), source })?;
+        entries.map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .map_err(|source| ImportError::Io { path: dir.to_owned(), source })?
+    } else {
+        let paths = glob::glob(path).map_err(|source| ImportError::Pattern { pattern: path.to_owned(), source })?;
+        paths.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|err| ImportError::Io { path: err.path().to_owned(), source: err.into() })?
+    };
+    Ok(files.into_iter().filter(|file| file.is_file()).collect())
+}
+
+/// Time a saved document was captured, shown in its file name in the time zone of the target
+/// as matched by `pattern`, or else the time the file was last modified
+pub(crate) fn capture_time(file: &Path, pattern: &Regex, timezone: Tz) -> Result<DateTime<Utc>> {
+    let name = file.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let datetime = pattern.captures(&name).and_then(|captures| {
+        let number = |group| captures.name(group).and_then(|number| number.as_str().parse().ok());
+        NaiveDate::from_ymd_opt(number("y")? as i32, number("m")?, number("d")?)?
+            .and_hms_opt(number("h")?, number("min")?, number("s").unwrap_or(0))
+    });
+    match datetime {
+        Some(datetime) => timezone.from_local_datetime(&datetime).earliest()
+            .map(|captured| captured.with_timezone(&Utc))
+            .ok_or(ImportError::NonexistentCaptureTime { path: file.to_owned(), datetime, timezone }),
+        None => file_modified(&file.to_owned()).map_err(|source| ImportError::Io { path: file.to_owned(), source }),
+    }
+}
+
+/// Regex of capture times in file names, which needs the groups `y`, `m`, `d`, `h` and `min`
+pub(crate) fn parse_time_pattern(pattern: &str) -> std::result::Result<Regex, String> {
+    let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+    match ["y", "m", "d", "h", "min"].iter().find(|group| !regex.capture_names().flatten().any(|name| name == **group)) {
+        Some(group) => Err(format!("capture group {group} missing")),
+        None => Ok(regex),
+    }
+}
+
+#[cfg(test)]
+mod tests;
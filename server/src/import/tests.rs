@@ -0,0 +1,72 @@
+use super::*;
+use crate::testing::scratch;
+use chrono_tz::Europe::Berlin;
+use std::time::{Duration, SystemTime};
+
+fn utc(month: u32, day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2023, month, day, hour, min, sec).unwrap()
+}
+
+fn captured(name: &str) -> Result<DateTime<Utc>> {
+    capture_time(Path::new(name), &parse_time_pattern(DEFAULT_TIME_PATTERN).unwrap(), Berlin)
+}
+
+#[test]
+fn capture_times_are_read_from_file_names() {
+    assert_eq!(captured("e5-2023-07-01T12-30-00.html").unwrap(), utc(7, 1, 10, 30, 0));
+    assert_eq!(captured("downloads/20230701_1230.html").unwrap(), utc(7, 1, 10, 30, 0));
+    assert_eq!(captured("diesel 2023-12-31 23.59.59.html").unwrap(), utc(12, 31, 22, 59, 59));
+}
+
+#[test]
+fn capture_times_skipped_by_dst_are_rejected() {
+    // clocks went forward from 02:00 to 03:00
+    assert!(matches!(captured("e5-2023-03-26T02-30.html"), Err(ImportError::NonexistentCaptureTime { .. })));
+    // of times shown twice the earlier one is taken
+    assert_eq!(captured("e5-2023-10-29T02-30.html").unwrap(), utc(10, 29, 0, 30, 0));
+}
+
+#[test]
+fn capture_times_fall_back_to_the_modification_time() {
+    let dir = scratch("import-modified");
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(utc(7, 1, 10, 30, 0).timestamp() as u64);
+    for name in ["e5.html", "e5-2023-13-01T12-30.html"] {
+        let file = fs::File::create(dir.join(name)).unwrap();
+        file.set_modified(modified).unwrap();
+        assert_eq!(captured(dir.join(name).to_str().unwrap()).unwrap(), utc(7, 1, 10, 30, 0), "{name}");
+    }
+    assert!(matches!(captured(dir.join("missing.html").to_str().unwrap()), Err(ImportError::Io { .. })));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn time_patterns_need_the_date_and_time_groups() {
+    let pattern = parse_time_pattern(r"(?P<d>\d{2})\.(?P<m>\d{2})\.(?P<y>\d{4}) (?P<h>\d{2})h(?P<min>\d{2})").unwrap();
+    let captured = capture_time(Path::new("e5 01.07.2023 12h30.html"), &pattern, Berlin).unwrap();
+    assert_eq!(captured, utc(7, 1, 10, 30, 0));
+    assert_eq!(parse_time_pattern(r"(?P<y>\d{4})(?P<m>\d{2})(?P<d>\d{2})(?P<h>\d{2})").unwrap_err(), "capture group min missing");
+    assert!(parse_time_pattern(r"(?P<y>\d{4}").is_err());
+}
+
+#[test]
+fn saved_documents_are_the_files_of_a_directory_or_glob() {
+    let dir = scratch("import-documents");
+    fs::create_dir(dir.join("e5")).unwrap();
+    for name in ["e5-1.html", "e5-2.html", "diesel-1.html", "e5/e5-3.html"] {
+        fs::write(dir.join(name), "").unwrap();
+    }
+    let names = |path: &Path| {
+        let mut files: Vec<_> = saved_documents(path.to_str().unwrap()).unwrap().into_iter()
+            .map(|file| file.strip_prefix(&dir).unwrap().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+        files
+    };
+
+    assert_eq!(names(&dir), vec!["diesel-1.html", "e5-1.html", "e5-2.html"]);
+    assert_eq!(names(&dir.join("e5*")), vec!["e5-1.html", "e5-2.html"]);
+    assert_eq!(names(&dir.join("**/e5-*.html")), vec!["e5-1.html", "e5-2.html", "e5/e5-3.html"]);
+    assert!(names(&dir.join("lpg-*.html")).is_empty());
+    assert!(matches!(saved_documents(dir.join("e5-[.html").to_str().unwrap()), Err(ImportError::Pattern { .. })));
+    fs::remove_dir_all(dir).unwrap();
+}
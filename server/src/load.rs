@@ -1,5 +1,4 @@
-use scraper::Html;
-
+use chrono::{DateTime, Utc};
 use std::io;
 use std::fs;
 use std::path::PathBuf;
@@ -7,9 +6,14 @@ use std::path::PathBuf;
 use tracing::info;
 
 #[tracing::instrument]
-pub(crate) async fn load_file(filename: &PathBuf) -> io::Result<Html> {
+pub(crate) async fn load_file(filename: &PathBuf) -> io::Result<String> {
     let document = fs::read_to_string(filename)?;
-    let document = Html::parse_document(&document);
     info!("document load from file");
     Ok(document)
 }
+
+/// Time a downloaded document was saved, which is when it was scraped
+pub(crate) fn file_modified(filename: &PathBuf) -> io::Result<DateTime<Utc>> {
+    let modified = fs::metadata(filename)?.modified()?;
+    Ok(modified.into())
+}
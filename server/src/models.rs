@@ -1,53 +1,358 @@
-use super::schema::price_changes;
+use super::db::{with_backend, DbConnection};
+use super::fuel::FuelType;
+use super::schema::{observations, price_changes, stations};
 
 use diesel::prelude::*;
+use diesel::sql_types::Integer;
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 
-#[derive(Queryable)]
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub(crate) struct RefuelStation {
+    pub id: i32,
+    pub name: String,
+    pub addr: String,
+}
+
+/// Price change as scraped from the webapp
+#[derive(Clone)]
 pub(crate) struct RefuelStationPriceChange {
     pub name: String,
     pub addr: String,
+    pub fuel: FuelType,
     pub updated: DateTime<Utc>,
-    pub price: u16,
+    /// Time of the scrape showing the price
+    pub observed: DateTime<Utc>,
+    /// Price in thousandths of the currency unit, e.g. tenths of a cent, always positive
+    pub price: i64,
+    /// ISO 4217 currency code like `EUR`
+    pub currency: String,
+}
+
+/// Price change as stored in the database
+#[derive(Clone)]
+pub(crate) struct PriceChange {
+    pub station: RefuelStation,
+    pub fuel: FuelType,
+    pub updated: DateTime<Utc>,
+    pub price: i64,
+    pub currency: String,
+}
+
+/// What saving a scraped price change would do
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SaveEffect {
+    /// Inserts the price change
+    New,
+    /// Nothing but recording the observation, the price is the last known one of the station
+    Unchanged,
+    /// Nothing, another price is saved for the same station, fuel type and time
+    Conflicting { price: i64, currency: String },
+}
+
+#[derive(Queryable)]
+struct PriceChangeRow {
+    station: RefuelStation,
+    fuel: FuelType,
+    updated: NaiveDateTime,
+    price: i64,
+    currency: String,
 }
 
 #[derive(Insertable)]
-#[diesel(table_name = price_changes)]
-struct NewRefuelStationPriceChange<'a> {
+#[diesel(table_name = stations)]
+struct NewRefuelStation<'a> {
     name: &'a str,
     addr: &'a str,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = price_changes)]
+struct NewPriceChange<'a> {
+    station_id: i32,
+    fuel: FuelType,
+    updated: NaiveDateTime,
+    price: i64,
+    currency: &'a str,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = observations)]
+struct NewObservation<'a> {
+    station_id: i32,
+    fuel: FuelType,
+    observed: NaiveDateTime,
     updated: NaiveDateTime,
-    price: i32,
+    price: i64,
+    currency: &'a str,
+}
+
+impl RefuelStation {
+    pub(crate) fn load_all(conn: &mut DbConnection) -> QueryResult<Vec<Self>> {
+        use crate::schema::stations::dsl::*;
+
+        let query = stations
+            .filter(merged_into.is_null())
+            .select((id, name, addr))
+            .order((name, addr));
+        with_backend!(conn, conn => query.load(conn))
+    }
+
+    pub(crate) fn load(conn: &mut DbConnection, station_id: i32) -> QueryResult<Option<Self>> {
+        use crate::schema::stations::dsl::*;
+
+        let query = stations
+            .filter(id.eq(station_id))
+            .select((id, merged_into));
+        let station: Option<(i32, Option<i32>)> = with_backend!(conn, conn => query.first(conn).optional())?;
+        match station {
+            Some((station_id, merged)) => Self::load_resolved(conn, merged.unwrap_or(station_id)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Station known under the given name and address, following links to renamed stations
+    pub(crate) fn find(conn: &mut DbConnection, station_name: &str, station_addr: &str) -> QueryResult<Option<Self>> {
+        use crate::schema::stations::dsl::*;
+
+        let query = stations
+            .filter(name.eq(station_name))
+            .filter(addr.eq(station_addr))
+            .select(id);
+        let station_id: Option<i32> = with_backend!(conn, conn => query.first(conn).optional())?;
+        match station_id {
+            Some(station_id) => Self::load(conn, station_id),
+            None => Ok(None),
+        }
+    }
+
+    fn find_or_create(conn: &mut DbConnection, station_name: &str, station_addr: &str) -> QueryResult<Self> {
+        use crate::schema::stations::dsl::*;
+
+        let insert = diesel::insert_into(stations)
+            .values(NewRefuelStation { name: station_name, addr: station_addr })
+            .on_conflict_do_nothing();
+        with_backend!(conn, conn => insert.execute(conn))?;
+        Self::find(conn, station_name, station_addr)?.ok_or(diesel::result::Error::NotFound)
+    }
+
+    fn load_resolved(conn: &mut DbConnection, station_id: i32) -> QueryResult<Self> {
+        use crate::schema::stations::dsl::*;
+
+        let query = stations
+            .filter(id.eq(station_id))
+            .select((id, name, addr));
+        with_backend!(conn, conn => query.first(conn))
+    }
+
+    /// Links the station `old_id` to the unlinked station `new_id`, e.g. after the webapp renamed it.
+    /// The price history and observations of the old station are moved to the new one and
+    /// later scrapes of the old name and address resolve to the new station.
+    pub(crate) fn link(conn: &mut DbConnection, old_id: i32, new_id: i32) -> QueryResult<()> {
+        let merge = diesel::update(stations::table.filter(stations::id.eq(old_id).or(stations::merged_into.eq(old_id))))
+            .set(stations::merged_into.eq(new_id));
+        let move_history = diesel::insert_into(price_changes::table)
+            .values(price_changes::table
+                .filter(price_changes::station_id.eq(old_id))
+                .select((new_id.into_sql::<Integer>(), price_changes::fuel, price_changes::updated,
+                         price_changes::price, price_changes::currency)))
+            .into_columns((price_changes::station_id, price_changes::fuel, price_changes::updated,
+                           price_changes::price, price_changes::currency))
+            .on_conflict_do_nothing();
+        let delete_history = diesel::delete(price_changes::table.filter(price_changes::station_id.eq(old_id)));
+        let move_observations = diesel::insert_into(observations::table)
+            .values(observations::table
+                .filter(observations::station_id.eq(old_id))
+                .select((new_id.into_sql::<Integer>(), observations::fuel, observations::observed,
+                         observations::updated, observations::price, observations::currency)))
+            .into_columns((observations::station_id, observations::fuel, observations::observed,
+                           observations::updated, observations::price, observations::currency))
+            .on_conflict_do_nothing();
+        let delete_observations = diesel::delete(observations::table.filter(observations::station_id.eq(old_id)));
+
+        conn.transaction(|conn| with_backend!(conn, conn => {
+            merge.execute(conn)?;
+            move_history.execute(conn)?;
+            delete_history.execute(conn)?;
+            move_observations.execute(conn)?;
+            delete_observations.execute(conn)?;
+            Ok(())
+        }))
+    }
 }
 
 impl RefuelStationPriceChange {
-    pub(crate) fn save(&self, conn: &mut SqliteConnection) -> bool {
-        let new = NewRefuelStationPriceChange::from(self);
-        new.insert(conn)
+    /// Saves the observation of the price and the price change unless the price is the last known one
+    /// of the station, returns the stored price change if it was not known before
+    pub(crate) fn save(&self, conn: &mut DbConnection) -> QueryResult<Option<PriceChange>> {
+        let station = RefuelStation::find_or_create(conn, &self.name, &self.addr)?;
+        let observation = NewObservation {
+            station_id: station.id,
+            fuel: self.fuel,
+            observed: self.observed.naive_utc(),
+            updated: self.updated.naive_utc(),
+            price: self.price,
+            currency: &self.currency,
+        };
+        observation.insert(conn)?;
+        if self.effect_after(self.last_known_of(conn, station.id)?) != SaveEffect::New {
+            return Ok(None);
+        }
+
+        let new = NewPriceChange {
+            station_id: station.id,
+            fuel: self.fuel,
+            updated: self.updated.naive_utc(),
+            price: self.price,
+            currency: &self.currency,
+        };
+        if new.insert(conn)? {
+            Ok(Some(PriceChange {
+                station,
+                fuel: self.fuel,
+                updated: self.updated,
+                price: self.price,
+                currency: self.currency.clone(),
+            }))
+        } else {
+            Ok(None)
+        }
     }
 }
 
-impl<'a> NewRefuelStationPriceChange<'a> {
-    pub(crate) fn insert(self, conn: &mut SqliteConnection) -> bool {
+impl RefuelStationPriceChange {
+    /// Updated time, price and currency of the last known price change of the station
+    /// at the time of the price change, none of an unknown station
+    pub(crate) fn last_known(&self, conn: &mut DbConnection) -> QueryResult<Option<(DateTime<Utc>, i64, String)>> {
+        match RefuelStation::find(conn, &self.name, &self.addr)? {
+            Some(station) => self.last_known_of(conn, station.id),
+            None => Ok(None),
+        }
+    }
+
+    fn last_known_of(&self, conn: &mut DbConnection, station_id: i32) -> QueryResult<Option<(DateTime<Utc>, i64, String)>> {
+        let query = price_changes::table
+            .filter(price_changes::station_id.eq(station_id))
+            .filter(price_changes::fuel.eq(self.fuel))
+            .filter(price_changes::updated.le(self.updated.naive_utc()))
+            .order(price_changes::updated.desc())
+            .select((price_changes::updated, price_changes::price, price_changes::currency));
+        let last: Option<(NaiveDateTime, i64, String)> = with_backend!(conn, conn => query.first(conn).optional())?;
+        Ok(last.map(|(updated, price, currency)| (Utc.from_utc_datetime(&updated), price, currency)))
+    }
+
+    /// Compares the price with the last known one of the station at the time of the price change,
+    /// so a later "updated" time showing the same price is no price change
+    pub(crate) fn effect_after(&self, last: Option<(DateTime<Utc>, i64, String)>) -> SaveEffect {
+        match last {
+            Some((_, price, currency)) if price == self.price && currency == self.currency => SaveEffect::Unchanged,
+            Some((updated, price, currency)) if updated == self.updated => SaveEffect::Conflicting { price, currency },
+            _ => SaveEffect::New,
+        }
+    }
+}
+
+impl PriceChange {
+    /// Deletes all price changes and observations of a station and fuel type, returns how many price changes
+    pub(crate) fn delete(conn: &mut DbConnection, station: &RefuelStation, fuel: FuelType) -> QueryResult<usize> {
+        let delete = diesel::delete(price_changes::table
+            .filter(price_changes::station_id.eq(station.id))
+            .filter(price_changes::fuel.eq(fuel)));
+        let delete_observations = diesel::delete(observations::table
+            .filter(observations::station_id.eq(station.id))
+            .filter(observations::fuel.eq(fuel)));
+        conn.transaction(|conn| with_backend!(conn, conn => {
+            delete_observations.execute(conn)?;
+            delete.execute(conn)
+        }))
+    }
+
+    /// Latest price change of every station and fuel type, all fuel types if `fuels` is empty
+    pub(crate) fn load_current(conn: &mut DbConnection, fuels: &[FuelType]) -> QueryResult<Vec<Self>> {
+        let latest = diesel::alias!(price_changes as latest);
+        // found by the primary key for each row, instead of scanning the whole history
+        let latest_updated = latest
+            .filter(latest.field(price_changes::station_id).eq(price_changes::station_id))
+            .filter(latest.field(price_changes::fuel).eq(price_changes::fuel))
+            .select(diesel::dsl::max(latest.field(price_changes::updated)))
+            .single_value();
+        let rows: Vec<PriceChangeRow> = with_backend!(conn, conn => {
+            let mut query = price_changes::table
+                .inner_join(stations::table)
+                .select(((stations::id, stations::name, stations::addr), price_changes::fuel, price_changes::updated,
+                         price_changes::price, price_changes::currency))
+                .filter(price_changes::updated.nullable().eq(latest_updated))
+                .into_boxed();
+            if !fuels.is_empty() {
+                query = query.filter(price_changes::fuel.eq_any(fuels));
+            }
+
+            query
+                .order((stations::name, stations::addr, price_changes::fuel))
+                .load(conn)?
+        });
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    /// Price changes of one station within `[from, to]`, oldest first, all fuel types if `fuels` is empty
+    pub(crate) fn load_history(conn: &mut DbConnection, station: &RefuelStation, fuels: &[FuelType],
+                               from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> QueryResult<Vec<Self>> {
+        let rows: Vec<PriceChangeRow> = with_backend!(conn, conn => {
+            let mut query = price_changes::table
+                .inner_join(stations::table)
+                .select(((stations::id, stations::name, stations::addr), price_changes::fuel, price_changes::updated,
+                         price_changes::price, price_changes::currency))
+                .filter(price_changes::station_id.eq(station.id))
+                .into_boxed();
+            if !fuels.is_empty() {
+                query = query.filter(price_changes::fuel.eq_any(fuels));
+            }
+            if let Some(from) = from {
+                query = query.filter(price_changes::updated.ge(from.naive_utc()));
+            }
+            if let Some(to) = to {
+                query = query.filter(price_changes::updated.le(to.naive_utc()));
+            }
+
+            query
+                .order((price_changes::updated.asc(), price_changes::fuel))
+                .load(conn)?
+        });
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+}
+
+impl NewPriceChange<'_> {
+    pub(crate) fn insert(self, conn: &mut DbConnection) -> QueryResult<bool> {
         use crate::schema::price_changes::dsl::*;
 
-        let inserted = diesel::insert_into(price_changes)
+        let insert = diesel::insert_into(price_changes)
+            .values(self)
+            .on_conflict_do_nothing();
+        let inserted = with_backend!(conn, conn => insert.execute(conn))?;
+        Ok(inserted > 0)
+    }
+}
+
+impl NewObservation<'_> {
+    /// Inserts the observation unless the same scrape was saved before, e.g. by a reparse
+    fn insert(self, conn: &mut DbConnection) -> QueryResult<()> {
+        let insert = diesel::insert_into(observations::table)
             .values(self)
-            .on_conflict_do_nothing()
-            .execute(conn)
-            .expect("Error saving new station");
-        inserted > 0
+            .on_conflict_do_nothing();
+        with_backend!(conn, conn => insert.execute(conn))?;
+        Ok(())
     }
 }
 
-impl<'a> From<&'a RefuelStationPriceChange> for NewRefuelStationPriceChange<'a> {
-    fn from(src: &'a RefuelStationPriceChange) -> Self {
+impl From<PriceChangeRow> for PriceChange {
+    fn from(src: PriceChangeRow) -> Self {
         Self {
-            name: &src.name,
-            addr: &src.addr,
-            updated: src.updated.naive_utc(),
-            price: src.price.into(),
+            station: src.station,
+            fuel: src.fuel,
+            updated: Utc.from_utc_datetime(&src.updated),
+            price: src.price,
+            currency: src.currency,
         }
     }
 }
@@ -1,78 +1,90 @@
 use crate::error::ParseError;
+use crate::fuel::FuelType;
 use crate::models::RefuelStationPriceChange;
+use crate::profile::{self, SiteProfile};
+use crate::source::{ItemError, ParsedDocument, PriceSource};
 
+use async_trait::async_trait;
 use scraper::{Html, ElementRef, Selector};
 
 use lazy_static::lazy_static;
-use regex::Regex;
-use chrono::{DateTime, TimeZone, Local};
-use std::collections::VecDeque;
+use regex::{Captures, Regex};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
 
 use tokio::try_join;
 
-use tracing::{debug, error};
+use tracing::debug;
 
 type Result<T> = std::result::Result<T, ParseError>;
 
-#[tracing::instrument(skip(document))]
-pub(crate) async fn parse(document: &Html) -> Result<VecDeque<RefuelStationPriceChange>> {
-    let selector_pricelist = Selector::parse(r#".PriceList"#).expect("invalid list selector");
-    let selector_priceitem = Selector::parse(r#".PriceList__item:not(.list-ad)"#).expect("invalid list item selector");
-    let selector_name = Selector::parse(r#".PriceList__itemTitle"#).expect("invalid name selector");
-    let selector_addr = Selector::parse(r#".PriceList__itemSubtitle"#).expect("invalid addr selector");
-    let selector_updated = Selector::parse(r#".PriceList__itemUpdated"#).expect("invalid updated selector");
-    let selector_price = Selector::parse(r#".PriceList__itemPrice"#).expect("invalid price selector");
-
-    let mut refuel_stations = VecDeque::with_capacity(20);
-    let document = document.select(&selector_pricelist).next().expect("list not found");
-    for elem in document.select(&selector_priceitem) {
-        let name = parse_text(&elem, &selector_name);
-        let addr = parse_text(&elem, &selector_addr);
-        let updated = parse_updated(&elem, &selector_updated);
-        let price = parse_price(&elem, &selector_price);
+/// Html price list, by default using the `.PriceList__*` classes
+pub(crate) struct PriceListSource {
+    pub profile: Arc<SiteProfile>,
+    /// Time zone of the times shown in the price list
+    pub timezone: Tz,
+}
+
+#[async_trait(?Send)]
+impl PriceSource for PriceListSource {
+    async fn parse(&self, document: &str, fuel: FuelType, scraped: DateTime<Utc>) -> Result<ParsedDocument> {
+        let document = Html::parse_document(document);
+        parse(&document, &self.profile, fuel, scraped.with_timezone(&self.timezone)).await
+    }
+}
+
+/// Parses the price list of a document scraped at `now`, which dates the shown times
+/// and gives the time zone they are shown in
+#[tracing::instrument(skip(document, profile))]
+pub(crate) async fn parse(document: &Html, profile: &SiteProfile, fuel: FuelType, now: DateTime<Tz>) -> Result<ParsedDocument> {
+    let mut parsed = ParsedDocument::default();
+    let document = document.select(&profile.list).next().ok_or(ParseError::ListNotFoundError {
+            selector: profile.list.clone(),
+        })?;
+    for (item, elem) in document.select(&profile.item).enumerate() {
+        let name = parse_text(&elem, &profile.name, &profile.text_regex);
+        let addr = parse_text(&elem, &profile.addr, &profile.text_regex);
+        let updated = parse_updated(&elem, &profile.updated, &profile.updated_regex, now);
+        let price = parse_price(&elem, &profile.price, &profile.price_regex, &profile.price_invalid_regex, &profile.currency);
 
         match try_join!(name, addr, price, updated) {
-            Ok((name, addr, price, updated)) => {
-                let updated = updated.into();
-                refuel_stations.push_back(RefuelStationPriceChange { name, addr, price, updated });
-            }
-            Err(err) => {
-                match err {
-                    ParseError::InvalidPriceError { html: _, regex: _ } |
-                    ParseError::InvalidUpdatedError { html: _, regex: _ } => {
-                        debug!("{err}");
-                    }
-                    _ => {
-                        error!("{err}");
-                    }
-                }
+            Ok((name, addr, (price, currency), updated)) => {
+                let (updated, observed) = (updated.with_timezone(&Utc), now.with_timezone(&Utc));
+                parsed.stations.push_back(RefuelStationPriceChange { name, addr, fuel, price, currency, updated, observed });
             }
+            Err(error) => parsed.skipped.push(ItemError { item, error, sample: elem.html() }),
         }
     }
 
-    Ok(refuel_stations)
+    Ok(parsed)
 }
 
 #[tracing::instrument(skip(fragment))]
-async fn parse_text<'a, 'b>(fragment: &ElementRef<'a>, selector: &'b Selector) -> Result<String> {
-    lazy_static! {
-        static ref REGEX: Regex = Regex::new(r"\b.+\b").expect("invalid text regex");
-    }
-
+async fn parse_text<'a, 'b>(fragment: &ElementRef<'a>, selector: &'b Selector, regex: &'b Regex) -> Result<String> {
     let text = fragment.select(selector).next().ok_or(ParseError::HtmlSelectError{
             html: fragment.inner_html(),
             selector: selector.clone(),
         })?;
     let text = text.inner_html();
-    let text = REGEX.find_iter(&text).map(|mat| mat.as_str()).next().expect("text regex mismatch");
-    Ok(text.to_owned())
+    let text = regex.find(&text).ok_or_else(|| ParseError::RegexMismatchError {
+            html: text.clone(),
+            regex: regex.clone(),
+        })?;
+    Ok(text.as_str().to_owned())
 }
 
 #[tracing::instrument(skip(fragment))]
-async fn parse_updated<'a, 'b>(fragment: &ElementRef<'a>, selector: &'b Selector) -> Result<DateTime<Local>> {
+async fn parse_updated<'a, 'b>(fragment: &ElementRef<'a>, selector: &'b Selector, regex: &'b Regex,
+                               now: DateTime<Tz>) -> Result<DateTime<Tz>> {
     lazy_static! {
-        static ref REGEX: Regex = Regex::new(r#"(?P<d>\d{2})\.(?P<m>\d{2})\..(?P<h>\d{2}):(?P<min>\d{2})"#).expect("invalid updated regex");
         static ref REGEX_WS: Regex = Regex::new(r#"^\s*$"#).expect("invalid updated regex");
+        static ref REGEX_NOW: Regex = Regex::new(r#"(?i)\b(gerade|soeben|jetzt)\b"#).expect("invalid now regex");
+        static ref REGEX_AGO: Regex = Regex::new(r#"(?i)\bvor\s+(?P<n>\d+|ein(e[mr]?)?)\s+(?P<unit>sek|min|std|stunde|tag)"#).expect("invalid ago regex");
+        static ref REGEX_DAY: Regex = Regex::new(r#"(?i)\b(?P<day>heute|gestern)\b\D*(?P<h>\d{1,2}):(?P<min>\d{2})"#).expect("invalid day regex");
     }
 
     let updated = fragment.select(selector).next().ok_or(ParseError::HtmlSelectError{
@@ -88,60 +100,159 @@ async fn parse_updated<'a, 'b>(fragment: &ElementRef<'a>, selector: &'b Selector
         });
     }
 
-    let updated = REGEX.captures(&updated).ok_or(ParseError::RegexMismatchError{
-            html: updated.to_owned(),
-            regex: REGEX.clone(),
-        })?;
+    // relative labels are rounded down to whole minutes, so scraping them twice yields the same time
+    let minute = now.with_second(0).and_then(|now| now.with_nanosecond(0)).unwrap_or(now);
 
-    let year = if let Some(year) = updated.name("y") {
-            year.as_str().parse()?
+    if let Some(captures) = regex.captures(&updated) {
+        let month = capture(&captures, "updated", regex, "m")?;
+        let day = capture(&captures, "updated", regex, "d")?;
+        let hour = capture(&captures, "updated", regex, "h")?;
+        let min = capture(&captures, "updated", regex, "min")?;
+        let sec = if let Some(sec) = captures.name("s") {
+                sec.as_str().parse()?
+            } else {
+                0
+            };
+        let time = NaiveTime::from_hms_opt(hour, min, sec).ok_or_else(|| ParseError::InvalidDateError(updated.clone()))?;
+
+        // expect datetime shown in the time zone of the target
+        if let Some(year) = captures.name("y") {
+            let date = NaiveDate::from_ymd_opt(year.as_str().parse()?, month, day)
+                .ok_or_else(|| ParseError::InvalidDateError(updated.clone()))?;
+            local_datetime(date.and_time(time), now)
         } else {
-            2023
+            infer_year(month, day, time, now)?.ok_or(ParseError::InvalidDateError(updated))
+        }
+    } else if REGEX_NOW.is_match(&updated) {
+        Ok(minute)
+    } else if let Some(captures) = REGEX_AGO.captures(&updated) {
+        let n = captures.name("n").map_or("", |n| n.as_str());
+        let n: u64 = if n.starts_with(|c: char| c.is_ascii_digit()) { n.parse()? } else { 1 };
+        let unit = match captures.name("unit").map_or("", |unit| unit.as_str()).to_lowercase().as_str() {
+            "sek" => 1,
+            "min" => 60,
+            "std" | "stunde" => 60 * 60,
+            _ => 24 * 60 * 60,
         };
-    let month = updated.name("m").expect("month missing in regex").as_str().parse().unwrap();
-    let day = updated.name("d").expect("day missing in regex").as_str().parse().unwrap();
-    let hour = updated.name("h").expect("day missing in regex").as_str().parse().unwrap();
-    let min = updated.name("min").expect("day missing in regex").as_str().parse().unwrap();
-    let sec = if let Some(sec) = updated.name("s") {
-            sec.as_str().parse()?
-        } else {
-            0
+        // a garbled label may be ages ago, beyond any time chrono can represent,
+        // and seconds are dropped like the ones of the scrape, so "vor 90 Sek." is a minute ago
+        n.checked_mul(unit)
+            .and_then(|secs| ChronoDuration::from_std(StdDuration::from_secs(secs - secs % 60)).ok())
+            .and_then(|ago| minute.checked_sub_signed(ago))
+            .ok_or(ParseError::InvalidDateError(updated))
+    } else if let Some(captures) = REGEX_DAY.captures(&updated) {
+        let hour = capture(&captures, "day", &REGEX_DAY, "h")?;
+        let min = capture(&captures, "day", &REGEX_DAY, "min")?;
+        let time = NaiveTime::from_hms_opt(hour, min, 0).ok_or_else(|| ParseError::InvalidDateError(updated.clone()))?;
+        let date = match captures.name("day").map_or("", |day| day.as_str()).to_lowercase().as_str() {
+            "heute" => Some(now.date_naive()),
+            _ => now.date_naive().pred_opt(),
         };
+        let date = date.ok_or_else(|| ParseError::InvalidDateError(updated.clone()))?;
+        local_datetime(date.and_time(time), now)
+    } else {
+        Err(ParseError::RegexMismatchError {
+            html: updated,
+            regex: regex.clone(),
+        })
+    }
+}
 
-    // expect datetime shown in local time
-    let datetime = Local.with_ymd_and_hms(year, month, day, hour, min, sec).unwrap();
-    Ok(datetime)
+/// Year of a date shown without one, relative to the time `now` the list was scraped.
+/// The latest date not after `now` is taken, so a list scraped in January dates
+/// a `31.12.` back to the last year. A day of tolerance keeps the next year for
+/// a `01.01.` scraped just before midnight of New Year's Eve with a lagging clock.
+fn infer_year(month: u32, day: u32, time: NaiveTime, now: DateTime<Tz>) -> Result<Option<DateTime<Tz>>> {
+    let Some(latest) = now.checked_add_signed(ChronoDuration::days(1)) else {
+        return Ok(None);
+    };
+    let latest = latest.naive_local();
+    let datetime = (now.year() - 1..=now.year() + 1).rev()
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .map(|date| date.and_time(time))
+        .find(|datetime| *datetime <= latest);
+    datetime.map(|datetime| local_datetime(datetime, now)).transpose()
 }
 
-#[tracing::instrument(skip(fragment))]
-async fn parse_price<'a, 'b>(fragment: &ElementRef<'a>, selector: &'b Selector) -> Result<u16> {
-    lazy_static! {
-        static ref REGEX_INVALID: Regex = Regex::new(r"[\-]\.[\-]{2}").expect("invalid invalid-price regex");
-        static ref REGEX: Regex = Regex::new(r"\b(?P<eur>\d)\.(?P<cent>\d{2})\b(?s:.+)\b(?P<subcent>\d)\b").expect("invalid price regex");
+/// Wall clock time of the time zone of `now` as shown in a price list scraped at `now`.
+/// Times repeated when the clocks go back are taken as the later one unless it is
+/// after `now`, as a shown time is never in the future. Times skipped when the
+/// clocks go forward can't be shown by a correct list and are reported as error.
+fn local_datetime(datetime: NaiveDateTime, now: DateTime<Tz>) -> Result<DateTime<Tz>> {
+    match now.timezone().from_local_datetime(&datetime) {
+        LocalResult::Single(datetime) => Ok(datetime),
+        LocalResult::Ambiguous(earlier, later) => {
+            let datetime = if later <= now { later } else { earlier };
+            debug!("ambiguous local time {datetime} taken as {}", datetime.with_timezone(&Utc));
+            Ok(datetime)
+        }
+        LocalResult::None => Err(ParseError::NonexistentLocalTimeError { datetime, timezone: now.timezone() }),
     }
+}
 
+#[tracing::instrument(skip(fragment))]
+async fn parse_price<'a, 'b>(fragment: &ElementRef<'a>, selector: &'b Selector, regex: &'b Regex, regex_invalid: &'b Regex,
+                             currency: &'b str) -> Result<(i64, String)> {
     let price = fragment.select(selector).next().ok_or(ParseError::HtmlSelectError{
             html: fragment.inner_html(),
             selector: selector.clone(),
         })?;
 
-    let price = price.inner_html();
+    let html = price.inner_html();
+
+    if regex_invalid.is_match(&html) {
+        return Err(ParseError::InvalidPriceError { html, regex: regex_invalid.clone() });
+    }
+
+    let captures = regex.captures(&html).ok_or_else(|| ParseError::RegexMismatchError{
+            html: html.clone(),
+            regex: regex.clone(),
+        })?;
+    let int = captures.name("int").ok_or_else(|| ParseError::MissingCaptureGroupError {
+            name: "price",
+            regex: regex.clone(),
+            group: "int",
+        })?;
+    let decimals: String = ["frac", "sup"].iter()
+        .filter_map(|group| captures.name(group))
+        .map(|decimals| decimals.as_str())
+        .collect();
+    let currency = match captures.name("cur") {
+        Some(currency) => profile::currency(currency.as_str())?,
+        None => currency.to_owned(),
+    };
 
-    if REGEX_INVALID.is_match(&price) {
-        return Err(ParseError::InvalidPriceError { html: price, regex: REGEX_INVALID.clone() });
+    match price_value(int.as_str(), &decimals) {
+        Some(price) if price > 0 => Ok((price, currency)),
+        _ => Err(ParseError::PriceRangeError { html }),
     }
+}
+
+/// Price in thousandths of the currency unit, e.g. tenths of a cent, given its integer part
+/// and decimals. Separators within the integer part are ignored, further decimals rounded.
+fn price_value(int: &str, decimals: &str) -> Option<i64> {
+    let int: String = int.chars().filter(char::is_ascii_digit).collect();
+    let int: i64 = int.parse().ok()?;
+    let mut decimals: Vec<i64> = decimals.chars()
+        .filter_map(|decimal| decimal.to_digit(10))
+        .map(i64::from)
+        .collect();
+    decimals.resize(decimals.len().max(4), 0);
+    let round = if decimals[3] >= 5 { 1 } else { 0 };
+    let thousandths = decimals[0] * 100 + decimals[1] * 10 + decimals[2] + round;
+    int.checked_mul(1000)?.checked_add(thousandths)
+}
 
-    let price = REGEX.captures(&price).ok_or(ParseError::RegexMismatchError{
-            html: price.to_owned(),
-            regex: REGEX.clone(),
+/// Number captured by `group` of the regex `name`
+fn capture<T: FromStr<Err = ParseIntError>>(captures: &Captures, name: &'static str, regex: &Regex, group: &'static str) -> Result<T> {
+    let capture = captures.name(group).ok_or_else(|| ParseError::MissingCaptureGroupError {
+            name,
+            regex: regex.clone(),
+            group,
         })?;
-    let eur: u16 = price.name("eur").expect("eur missing in regex").as_str().parse().expect("eur is no integer");
-    let cent: u16 = price.name("cent").expect("cent missing in regex").as_str().parse().expect("cent is no integer");
-    let subcent: u16 = price.name("subcent").expect("subcent missing in regex").as_str().parse().expect("subcent is no integer");
-    assert!(subcent < 10);
-    assert!(cent < 100);
-
-    let price = eur * 1000 + cent * 10 + subcent;
-    Ok(price)
+    Ok(capture.as_str().parse()?)
 }
 
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,172 @@
+use super::*;
+use chrono_tz::Europe::Berlin;
+
+/// Parses a saved fragment of `tests/fragments` as if it was scraped at `now`
+async fn parse_fragment(fragment: &str, now: DateTime<Tz>) -> Vec<(String, i64, DateTime<Tz>)> {
+    let document = Html::parse_document(fragment);
+    parse(&document, &SiteProfile::builtin(), FuelType::E5, now).await
+        .expect("fragment not parsed")
+        .stations
+        .into_iter()
+        .map(|rs| (rs.name, rs.price, rs.updated.with_timezone(&now.timezone())))
+        .collect()
+}
+
+fn local(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Tz> {
+    Berlin.with_ymd_and_hms(year, month, day, hour, min, sec).unwrap()
+}
+
+#[tokio::test]
+async fn absolute_dates_use_the_year_of_the_scrape() {
+    let stations = parse_fragment(include_str!("../../tests/fragments/absolute.html"), local(2026, 10, 18, 12, 0, 0)).await;
+    assert_eq!(stations, vec![
+        ("MyESSO".to_owned(), 1759, local(2026, 10, 18, 9, 59, 0)),
+        ("MyJET".to_owned(), 1769, local(2026, 10, 17, 21, 3, 0)),
+    ]);
+}
+
+#[tokio::test]
+async fn december_scraped_in_january_is_last_year() {
+    let stations = parse_fragment(include_str!("../../tests/fragments/new_year.html"), local(2027, 1, 1, 0, 10, 0)).await;
+    assert_eq!(stations[0].2, local(2026, 12, 31, 23, 58, 0));
+    assert_eq!(stations[1].2, local(2027, 1, 1, 0, 3, 0));
+}
+
+#[tokio::test]
+async fn january_scraped_on_new_years_eve_is_next_year() {
+    let stations = parse_fragment(include_str!("../../tests/fragments/new_year.html"), local(2026, 12, 31, 23, 59, 0)).await;
+    assert_eq!(stations[0].2, local(2026, 12, 31, 23, 58, 0));
+    assert_eq!(stations[1].2, local(2027, 1, 1, 0, 3, 0));
+}
+
+#[tokio::test]
+async fn relative_labels_are_relative_to_the_scrape() {
+    let stations = parse_fragment(include_str!("../../tests/fragments/relative.html"), local(2026, 1, 1, 15, 0, 42)).await;
+    let updated: Vec<_> = stations.into_iter().map(|(_, _, updated)| updated).collect();
+    assert_eq!(updated, vec![
+        local(2026, 1, 1, 14, 35, 0),
+        local(2025, 12, 31, 23, 10, 0),
+        local(2026, 1, 1, 14, 55, 0),
+        local(2026, 1, 1, 14, 0, 0),
+        local(2026, 1, 1, 15, 0, 0),
+        local(2026, 1, 1, 14, 59, 0),
+        local(2026, 1, 1, 15, 0, 0),
+    ]);
+}
+
+#[tokio::test]
+async fn relative_labels_are_stable_within_a_minute() {
+    let fragment = include_str!("../../tests/fragments/relative.html");
+    let first = parse_fragment(fragment, local(2026, 6, 1, 12, 0, 1)).await;
+    let second = parse_fragment(fragment, local(2026, 6, 1, 12, 0, 59)).await;
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn relative_labels_ages_ago_are_invalid() {
+    let document = Html::parse_document(include_str!("../../tests/fragments/ages_ago.html"));
+    let parsed = parse(&document, &SiteProfile::builtin(), FuelType::E5, local(2026, 10, 18, 12, 0, 0)).await.unwrap();
+    assert_eq!(parsed.stations.len(), 1);
+    assert_eq!(parsed.stations[0].updated, local(2026, 10, 18, 11, 55, 0));
+
+    let skipped: Vec<_> = parsed.skipped.iter().map(|skipped| skipped.item).collect();
+    assert_eq!(skipped, vec![1, 2]);
+    assert!(parsed.skipped.iter().all(|skipped| matches!(skipped.error, ParseError::InvalidDateError(_))));
+}
+
+#[tokio::test]
+async fn relative_labels_of_any_size_are_parsed_or_skipped() {
+    let fragment = include_str!("../../tests/fragments/ages_ago.html");
+    for unit in ["Sek.", "Min.", "Std.", "Stunden", "Tagen"] {
+        for n in ["0", "1", "999999999", "9999999999999999", "18446744073709551615", "99999999999999999999"] {
+            let document = Html::parse_document(&fragment.replace("vor 5 Min.", &format!("vor {n} {unit}")));
+            let parsed = parse(&document, &SiteProfile::builtin(), FuelType::E5, local(2026, 10, 18, 12, 0, 0)).await.unwrap();
+            assert_eq!(parsed.stations.len() + parsed.skipped.len(), 3, "vor {n} {unit}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn times_skipped_by_dst_are_rejected() {
+    // 29.03. 02:30 does not exist in Berlin as the clocks go forward at 02:00
+    let stations = parse_fragment(include_str!("../../tests/fragments/dst.html"), local(2026, 10, 25, 12, 0, 0)).await;
+    assert_eq!(stations.len(), 1);
+    assert_eq!(stations[0].0, "MyJET");
+
+    let datetime = NaiveDate::from_ymd_opt(2026, 3, 29).unwrap().and_hms_opt(2, 30, 0).unwrap();
+    assert!(matches!(local_datetime(datetime, local(2026, 3, 29, 12, 0, 0)),
+                     Err(ParseError::NonexistentLocalTimeError { .. })));
+}
+
+#[test]
+fn times_repeated_by_dst_are_never_in_the_future() {
+    // 25.10. 02:30 exists twice in Berlin as the clocks go back at 03:00
+    let datetime = NaiveDate::from_ymd_opt(2026, 10, 25).unwrap().and_hms_opt(2, 30, 0).unwrap();
+    let summer = Utc.with_ymd_and_hms(2026, 10, 25, 0, 30, 0).unwrap();
+    let winter = Utc.with_ymd_and_hms(2026, 10, 25, 1, 30, 0).unwrap();
+
+    // scraped at 02:40 summer time, before the clocks went back
+    let now = Utc.with_ymd_and_hms(2026, 10, 25, 0, 40, 0).unwrap().with_timezone(&Berlin);
+    assert_eq!(local_datetime(datetime, now).unwrap(), summer);
+
+    // scraped at 02:40 winter time, after the clocks went back
+    let now = Utc.with_ymd_and_hms(2026, 10, 25, 1, 40, 0).unwrap().with_timezone(&Berlin);
+    assert_eq!(local_datetime(datetime, now).unwrap(), winter);
+}
+
+#[tokio::test]
+async fn times_are_shown_in_the_time_zone_of_the_target() {
+    let fragment = include_str!("../../tests/fragments/absolute.html");
+    let now = Utc.with_ymd_and_hms(2026, 10, 18, 12, 0, 0).unwrap();
+    let berlin = parse_fragment(fragment, now.with_timezone(&Berlin)).await;
+    let lisbon = parse_fragment(fragment, now.with_timezone(&chrono_tz::Europe::Lisbon)).await;
+    assert_eq!(lisbon[0].2 - berlin[0].2, ChronoDuration::hours(1));
+}
+
+#[tokio::test]
+async fn odd_items_are_skipped_and_reported() {
+    let document = Html::parse_document(include_str!("../../tests/fragments/odd.html"));
+    let parsed = parse(&document, &SiteProfile::builtin(), FuelType::E5, local(2026, 10, 18, 12, 0, 0)).await.unwrap();
+    assert_eq!(parsed.stations.len(), 1);
+    assert_eq!(parsed.stations[0].name, "MyESSO");
+
+    let skipped: Vec<_> = parsed.skipped.iter().map(|skipped| skipped.item).collect();
+    assert_eq!(skipped, vec![1, 2, 3]);
+    assert!(matches!(parsed.skipped[0].error, ParseError::HtmlSelectError { .. }));
+    assert!(matches!(parsed.skipped[1].error, ParseError::InvalidDateError(_)));
+    assert!(parsed.skipped[2].error.is_unavailable());
+}
+
+#[tokio::test]
+async fn missing_list_is_an_error() {
+    let document = Html::parse_document("<html><body><p>Wartungsarbeiten</p></body></html>");
+    let parsed = parse(&document, &SiteProfile::builtin(), FuelType::E5, local(2026, 10, 18, 12, 0, 0)).await;
+    assert!(matches!(parsed, Err(ParseError::ListNotFoundError { .. })));
+}
+
+#[tokio::test]
+async fn prices_with_any_digits_separators_and_currencies() {
+    let document = Html::parse_document(include_str!("../../tests/fragments/prices.html"));
+    let parsed = parse(&document, &SiteProfile::builtin(), FuelType::Lpg, local(2026, 10, 18, 12, 0, 0)).await.unwrap();
+    let prices: Vec<_> = parsed.stations.into_iter().map(|rs| (rs.price, rs.currency)).collect();
+    assert_eq!(prices, vec![
+        (1759, "EUR".to_owned()),
+        (999, "EUR".to_owned()),
+        (1759, "EUR".to_owned()),
+        (12340, "PLN".to_owned()),
+        (1760, "EUR".to_owned()),
+        (65536, "EUR".to_owned()),
+    ]);
+}
+
+#[test]
+fn price_values_ignore_separators_and_round_further_decimals() {
+    assert_eq!(price_value("1", "759"), Some(1759));
+    assert_eq!(price_value("1", "75"), Some(1750));
+    assert_eq!(price_value("172", ""), Some(172000));
+    assert_eq!(price_value("1.234", "5"), Some(1234500));
+    assert_eq!(price_value("1", "7594"), Some(1759));
+    assert_eq!(price_value("1", "7595"), Some(1760));
+    assert_eq!(price_value("", "5"), None);
+    assert_eq!(price_value("99999999999999999999", "0"), None);
+}
@@ -0,0 +1,171 @@
+use crate::error::ParseError;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use scraper::Selector;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use tracing::info;
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+lazy_static! {
+    static ref BUILTIN: Arc<SiteProfile> =
+        Arc::new(SiteProfile::compile(&ProfileDefinition::default()).expect("invalid builtin site profile"));
+}
+
+/// Selectors and regexes of a site profile file, fields not given keep the builtin definition
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProfileDefinition {
+    /// Currency of prices shown without one
+    currency: String,
+    selectors: SelectorDefinitions,
+    regexes: RegexDefinitions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SelectorDefinitions {
+    list: String,
+    item: String,
+    name: String,
+    addr: String,
+    updated: String,
+    price: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RegexDefinitions {
+    text: String,
+    updated: String,
+    price: String,
+    price_invalid: String,
+}
+
+impl Default for ProfileDefinition {
+    fn default() -> Self {
+        Self {
+            currency: "EUR".to_owned(),
+            selectors: SelectorDefinitions::default(),
+            regexes: RegexDefinitions::default(),
+        }
+    }
+}
+
+impl Default for SelectorDefinitions {
+    fn default() -> Self {
+        Self {
+            list: r#".PriceList"#.to_owned(),
+            item: r#".PriceList__item:not(.list-ad)"#.to_owned(),
+            name: r#".PriceList__itemTitle"#.to_owned(),
+            addr: r#".PriceList__itemSubtitle"#.to_owned(),
+            updated: r#".PriceList__itemUpdated"#.to_owned(),
+            price: r#".PriceList__itemPrice"#.to_owned(),
+        }
+    }
+}
+
+impl Default for RegexDefinitions {
+    fn default() -> Self {
+        Self {
+            text: r"\b.+\b".to_owned(),
+            updated: r#"(?P<d>\d{2})\.(?P<m>\d{2})\..(?P<h>\d{2}):(?P<min>\d{2})"#.to_owned(),
+            price: r"(?P<int>\d+)[.,](?P<frac>\d+)(?:\s*<sup[^>]*>\s*(?P<sup>\d+)\s*</sup>)?(?:\s*(?P<cur>[A-Z]{3}|€))?".to_owned(),
+            price_invalid: r"-[.,]-+".to_owned(),
+        }
+    }
+}
+
+/// Compiled selectors and regexes used to parse an html price list
+#[derive(Clone, Debug)]
+pub(crate) struct SiteProfile {
+    /// ISO 4217 code of the currency of prices shown without one
+    pub currency: String,
+    /// Element containing the price list
+    pub list: Selector,
+    /// Entry of a single refuel station within the list
+    pub item: Selector,
+    pub name: Selector,
+    pub addr: Selector,
+    pub updated: Selector,
+    pub price: Selector,
+    /// Text within the name and addr elements
+    pub text_regex: Regex,
+    /// Captures `d`, `m`, `h`, `min` and optionally `y` and `s` of the updated element
+    pub updated_regex: Regex,
+    /// Captures `int`, the integer part, and optionally `frac`, the decimals, `sup`,
+    /// further decimals e.g. in a `<sup>`, and `cur`, the currency, of the price element
+    pub price_regex: Regex,
+    /// Matches a price element of a refuel station not selling the fuel right now
+    pub price_invalid_regex: Regex,
+}
+
+impl SiteProfile {
+    /// The profile of the `.PriceList__*` layout compiled into the binary
+    pub(crate) fn builtin() -> Arc<Self> {
+        BUILTIN.clone()
+    }
+
+    #[tracing::instrument]
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let profile = fs::read_to_string(path).map_err(|source| ParseError::ProfileIoError { path: path.to_owned(), source })?;
+        let profile: ProfileDefinition = toml::from_str(&profile).map_err(|source| ParseError::ProfileTomlError { path: path.to_owned(), source })?;
+        let profile = Self::compile(&profile)?;
+        info!("site profile loaded");
+        Ok(profile)
+    }
+
+    fn compile(def: &ProfileDefinition) -> Result<Self> {
+        let selectors = &def.selectors;
+        let regexes = &def.regexes;
+        Ok(Self {
+            currency: currency(&def.currency)?,
+            list: selector("list", &selectors.list)?,
+            item: selector("item", &selectors.item)?,
+            name: selector("name", &selectors.name)?,
+            addr: selector("addr", &selectors.addr)?,
+            updated: selector("updated", &selectors.updated)?,
+            price: selector("price", &selectors.price)?,
+            text_regex: regex("text", &regexes.text, &[])?,
+            updated_regex: regex("updated", &regexes.updated, &["d", "m", "h", "min"])?,
+            price_regex: regex("price", &regexes.price, &["int"])?,
+            price_invalid_regex: regex("price_invalid", &regexes.price_invalid, &[])?,
+        })
+    }
+}
+
+fn selector(name: &'static str, selector: &str) -> Result<Selector> {
+    Selector::parse(selector).map_err(|err| ParseError::InvalidSelectorError {
+        name,
+        selector: selector.to_owned(),
+        message: format!("{err:?}"),
+    })
+}
+
+/// ISO 4217 code of a currency code or symbol as shown in price lists
+pub(crate) fn currency(currency: &str) -> Result<String> {
+    match currency.trim() {
+        "€" => Ok("EUR".to_owned()),
+        "$" => Ok("USD".to_owned()),
+        "£" => Ok("GBP".to_owned()),
+        code if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) => Ok(code.to_ascii_uppercase()),
+        _ => Err(ParseError::UnknownCurrencyError(currency.to_owned())),
+    }
+}
+
+/// Compiles a regex and checks it has all the capture groups the parser relies on
+fn regex(name: &'static str, regex: &str, groups: &[&'static str]) -> Result<Regex> {
+    let regex = Regex::new(regex).map_err(|source| ParseError::InvalidRegexError { name, source })?;
+    if let Some(group) = groups.iter().find(|group| !regex.capture_names().flatten().any(|name| name == **group)) {
+        return Err(ParseError::MissingCaptureGroupError { name, regex, group });
+    }
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests;
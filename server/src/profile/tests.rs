@@ -0,0 +1,81 @@
+use super::*;
+
+/// Compiles a site profile definition given as toml
+fn compile(profile: &str) -> Result<SiteProfile> {
+    let profile: ProfileDefinition = toml::from_str(profile).expect("profile definition not deserialized");
+    SiteProfile::compile(&profile)
+}
+
+#[test]
+fn example_profile_is_the_builtin_one() {
+    let example = SiteProfile::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join("profile.example.toml")).unwrap();
+    let builtin = SiteProfile::builtin();
+    assert_eq!(example.currency, builtin.currency);
+    assert_eq!(example.item, builtin.item);
+    assert_eq!(example.updated_regex.as_str(), builtin.updated_regex.as_str());
+    assert_eq!(example.price_regex.as_str(), builtin.price_regex.as_str());
+}
+
+#[test]
+fn missing_fields_keep_the_builtin_definition() {
+    let profile = compile("currency = \"chf\"\n[selectors]\nitem = \".station\"\n[regexes]\nprice_invalid = 'n/a'").unwrap();
+    assert_eq!(profile.currency, "CHF");
+    assert_eq!(profile.item, Selector::parse(".station").unwrap());
+    assert_eq!(profile.price_invalid_regex.as_str(), "n/a");
+    assert_eq!(profile.list, SiteProfile::builtin().list);
+    assert_eq!(profile.price_regex.as_str(), SiteProfile::builtin().price_regex.as_str());
+}
+
+#[test]
+fn unreadable_files_are_rejected() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/missing.toml");
+    assert!(matches!(SiteProfile::load(&path), Err(ParseError::ProfileIoError { .. })));
+}
+
+#[test]
+fn invalid_toml_is_rejected() {
+    let profile = |profile| toml::from_str::<ProfileDefinition>(profile);
+    assert!(profile("[selectors\nitem = \".station\"").is_err());
+    assert!(profile("[selectors]\nitem = 1").is_err());
+    assert!(profile("[selectors]\nstation = \".station\"").is_err());
+    assert!(profile("currency = \"EUR\"\nlocale = \"de\"").is_err());
+
+    let path = std::env::temp_dir().join(format!("refuel-profile-syntax-{}.toml", std::process::id()));
+    fs::write(&path, "[regexes]\nprice =").unwrap();
+    assert!(matches!(SiteProfile::load(&path), Err(ParseError::ProfileTomlError { .. })));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn invalid_selectors_are_rejected() {
+    let compiled = compile("[selectors]\nprice = \".price >\"");
+    assert!(matches!(compiled, Err(ParseError::InvalidSelectorError { name: "price", selector, .. }) if selector == ".price >"));
+    assert!(matches!(compile("[selectors]\nlist = \"\""), Err(ParseError::InvalidSelectorError { name: "list", .. })));
+}
+
+#[test]
+fn invalid_regexes_are_rejected() {
+    assert!(matches!(compile("[regexes]\nupdated = '(?P<d>\\d{2}'"), Err(ParseError::InvalidRegexError { name: "updated", .. })));
+    assert!(matches!(compile("[regexes]\ntext = '['"), Err(ParseError::InvalidRegexError { name: "text", .. })));
+}
+
+#[test]
+fn regexes_need_the_capture_groups_of_the_parser() {
+    let compiled = compile("[regexes]\nupdated = '(?P<d>\\d{2})\\.(?P<m>\\d{2})\\. (?P<h>\\d{2}):\\d{2}'");
+    assert!(matches!(compiled, Err(ParseError::MissingCaptureGroupError { name: "updated", group: "min", .. })));
+    let compiled = compile("[regexes]\nprice = '(\\d+)[.,](?P<frac>\\d+)'");
+    assert!(matches!(compiled, Err(ParseError::MissingCaptureGroupError { name: "price", group: "int", .. })));
+    // optional groups may be left out
+    assert!(compile("[regexes]\nprice = '(?P<int>\\d+)'").is_ok());
+}
+
+#[test]
+fn currencies_are_iso_4217_codes() {
+    assert_eq!(currency("€").unwrap(), "EUR");
+    assert_eq!(currency(" $ ").unwrap(), "USD");
+    assert_eq!(currency("£").unwrap(), "GBP");
+    assert_eq!(currency("chf").unwrap(), "CHF");
+    assert!(matches!(currency("¥"), Err(ParseError::UnknownCurrencyError(symbol)) if symbol == "¥"));
+    assert!(matches!(currency("EURO"), Err(ParseError::UnknownCurrencyError(_))));
+    assert!(matches!(compile("currency = \"Euro\""), Err(ParseError::UnknownCurrencyError(_))));
+}
@@ -0,0 +1,171 @@
+use crate::db::DbConnection;
+use crate::fuel::FuelType;
+use crate::models::{RefuelStationPriceChange, SaveEffect};
+use crate::source::ParsedDocument;
+
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Maximum length of the html or json sample shown per skip reason
+const SAMPLE_LEN: usize = 2000;
+
+/// Summary of how well a document was understood, to catch site redesigns early
+#[derive(Debug, Default)]
+pub(crate) struct ParseReport {
+    /// Number of items parsed into price changes
+    pub parsed: usize,
+    /// Items skipped, by reason
+    pub skipped: BTreeMap<&'static str, SkipReason>,
+}
+
+/// Items skipped for the same reason
+#[derive(Debug)]
+pub(crate) struct SkipReason {
+    pub count: usize,
+    /// Whether the items just don't show a price right now, e.g. of closed refuel stations
+    pub unavailable: bool,
+    /// Error and html or json of the first item skipped
+    pub error: String,
+    pub sample: String,
+}
+
+impl ParseReport {
+    pub(crate) fn new(parsed: &ParsedDocument) -> Self {
+        let mut report = Self { parsed: parsed.stations.len(), skipped: BTreeMap::new() };
+        for skipped in parsed.skipped.iter() {
+            report.skipped.entry(skipped.error.reason())
+                .and_modify(|reason| reason.count += 1)
+                .or_insert_with(|| SkipReason {
+                    count: 1,
+                    unavailable: skipped.error.is_unavailable(),
+                    error: skipped.error.to_string().trim().to_owned(),
+                    sample: truncate(skipped.sample.trim(), SAMPLE_LEN),
+                });
+        }
+        report
+    }
+
+    /// Number of items found in the document
+    pub(crate) fn items(&self) -> usize {
+        self.parsed + self.skipped.values().map(|reason| reason.count).sum::<usize>()
+    }
+
+    /// Ratio of the items skipped for another reason than not showing a price right now,
+    /// a document without any items is entirely skipped
+    pub(crate) fn skip_ratio(&self) -> f64 {
+        let failed: usize = self.skipped.values()
+            .filter(|reason| !reason.unavailable)
+            .map(|reason| reason.count)
+            .sum();
+        match self.items() {
+            0 => 1.0,
+            items => failed as f64 / items as f64,
+        }
+    }
+}
+
+impl fmt::Display for ParseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "items:      {}", self.items())?;
+        writeln!(f, "parsed:     {}", self.parsed)?;
+        writeln!(f, "skipped:    {}", self.items() - self.parsed)?;
+        writeln!(f, "skip ratio: {:.1}% (without unavailable prices)", self.skip_ratio() * 100.0)?;
+        for (reason, skipped) in self.skipped.iter() {
+            let unavailable = if skipped.unavailable { ", unavailable price" } else { "" };
+            writeln!(f)?;
+            writeln!(f, "{reason}: {}{unavailable}", skipped.count)?;
+            for line in skipped.error.lines() {
+                writeln!(f, "  {line}")?;
+            }
+            writeln!(f, "  sample:")?;
+            for line in skipped.sample.lines() {
+                writeln!(f, "    {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What saving scraped price changes would do, shown by a dry run like a diff:
+/// `+` new price changes, `!` conflicting ones and unchanged ones only counted
+#[derive(Default)]
+pub(crate) struct PriceDiff {
+    changes: Vec<(SaveEffect, RefuelStationPriceChange)>,
+    pub new: usize,
+    pub unchanged: usize,
+    pub conflicting: usize,
+}
+
+/// Price changes a dry run would have saved so far, by station and fuel type, as the price changes
+/// compared later must be compared with them too, like a real run compares with the saved ones
+#[derive(Clone, Default)]
+pub(crate) struct PendingPrices {
+    prices: Arc<Mutex<HashMap<PriceKey, PriceHistory>>>,
+}
+
+/// Name and address of the station and fuel type
+type PriceKey = (String, String, FuelType);
+/// Price and currency by updated time
+type PriceHistory = BTreeMap<DateTime<Utc>, (i64, String)>;
+
+impl PriceDiff {
+    /// Compares the price changes with the database and the `pending` ones, all are new without either
+    pub(crate) fn new(refuel_stations: impl IntoIterator<Item = RefuelStationPriceChange>, pending: &PendingPrices,
+                      mut conn: Option<&mut DbConnection>) -> QueryResult<Self> {
+        let mut diff = Self::default();
+        let mut pending = pending.prices.lock().unwrap_or_else(PoisonError::into_inner);
+        for rs in refuel_stations {
+            let saved = match conn.as_deref_mut() {
+                Some(conn) => rs.last_known(conn)?,
+                None => None,
+            };
+            let prices = pending.entry((rs.name.clone(), rs.addr.clone(), rs.fuel)).or_default();
+            let unsaved = prices.range(..=rs.updated).next_back()
+                .map(|(updated, (price, currency))| (*updated, *price, currency.clone()));
+            let last = match (saved, unsaved) {
+                (Some(saved), Some(unsaved)) => Some(if unsaved.0 >= saved.0 { unsaved } else { saved }),
+                (saved, unsaved) => saved.or(unsaved),
+            };
+            let effect = rs.effect_after(last);
+            match effect {
+                SaveEffect::New => {
+                    diff.new += 1;
+                    prices.insert(rs.updated, (rs.price, rs.currency.clone()));
+                }
+                SaveEffect::Unchanged => diff.unchanged += 1,
+                SaveEffect::Conflicting { .. } => diff.conflicting += 1,
+            }
+            diff.changes.push((effect, rs));
+        }
+        Ok(diff)
+    }
+}
+
+impl fmt::Display for PriceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (effect, rs) in self.changes.iter() {
+            let station = format!("{} {} / {}: {} {:.3} {}", rs.updated.format("%Y-%m-%d %H:%M UTC"), rs.name, rs.addr,
+                                  rs.fuel, rs.price as f64 / 1000.0, rs.currency);
+            match effect {
+                SaveEffect::New => writeln!(f, "+ {station}")?,
+                SaveEffect::Unchanged => {}
+                SaveEffect::Conflicting { price, currency } =>
+                    writeln!(f, "! {station}, saved {:.3} {currency}", *price as f64 / 1000.0)?,
+            }
+        }
+        writeln!(f, "new: {}, unchanged: {}, conflicting: {}", self.new, self.unchanged, self.conflicting)
+    }
+}
+
+fn truncate(sample: &str, len: usize) -> String {
+    match sample.char_indices().nth(len) {
+        Some((end, _)) => format!("{}..", &sample[..end]),
+        None => sample.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests;
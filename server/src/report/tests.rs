@@ -0,0 +1,95 @@
+use super::*;
+use crate::error::ParseError;
+use crate::fuel::FuelType;
+use crate::source::ItemError;
+use crate::testing::price_change;
+
+/// Report of a document with `parsed` items parsed, `unavailable` without a price and `failed` of an unknown currency
+fn report(parsed: usize, unavailable: usize, failed: usize) -> ParseReport {
+    let missing = (0..unavailable).map(|item| ItemError {
+        item,
+        error: ParseError::MissingPriceError { name: format!("MyESSO {item}"), fuel: FuelType::E5 },
+        sample: format!("<li>MyESSO {item}</li>"),
+    });
+    let unknown = (0..failed).map(|item| ItemError {
+        item: unavailable + item,
+        error: ParseError::UnknownCurrencyError("¥".to_owned()),
+        sample: "x".repeat(SAMPLE_LEN + 10),
+    });
+    ParseReport::new(&ParsedDocument {
+        stations: (0..parsed).map(|item| price_change(&format!("MyJET {item}"), FuelType::E5, 0, 1759)).collect(),
+        skipped: missing.chain(unknown).collect(),
+    })
+}
+
+#[test]
+fn skipped_items_are_grouped_by_reason() {
+    let report = report(6, 2, 3);
+    assert_eq!(report.items(), 11);
+    let reasons: Vec<_> = report.skipped.iter().map(|(reason, skipped)| (*reason, skipped.count, skipped.unavailable)).collect();
+    assert_eq!(reasons, vec![("missing-price", 2, true), ("unknown-currency", 3, false)]);
+    assert_eq!(report.skipped["missing-price"].sample, "<li>MyESSO 0</li>");
+    assert_eq!(report.skipped["unknown-currency"].sample.chars().count(), SAMPLE_LEN + 2);
+}
+
+#[test]
+fn unavailable_prices_are_not_counted_as_skipped() {
+    assert_eq!(report(8, 2, 0).skip_ratio(), 0.0);
+    assert_eq!(report(6, 2, 2).skip_ratio(), 0.2);
+    assert_eq!(report(0, 0, 4).skip_ratio(), 1.0);
+    assert_eq!(report(0, 4, 0).skip_ratio(), 0.0);
+}
+
+#[test]
+fn documents_without_items_are_entirely_skipped() {
+    // e.g. a redesign renaming the classes of the items, which the canary must catch
+    assert_eq!(report(0, 0, 0).skip_ratio(), 1.0);
+    assert!(report(0, 0, 0).to_string().contains("skip ratio: 100.0%"));
+}
+
+#[test]
+fn reports_show_the_skip_ratio_and_samples() {
+    let shown = report(9, 0, 1).to_string();
+    assert!(shown.starts_with("items:      10\nparsed:     9\nskipped:    1\nskip ratio: 10.0% (without unavailable prices)\n"), "{shown}");
+    assert!(shown.contains("\nunknown-currency: 1\n"), "{shown}");
+    assert!(report(9, 1, 0).to_string().contains("\nmissing-price: 1, unavailable price\n"));
+}
+
+#[test]
+fn diffs_without_a_database_are_all_new() {
+    let changes = [price_change("MyESSO", FuelType::E5, 0, 1759), price_change("MyJET", FuelType::E5, 0, 1769)];
+    let diff = PriceDiff::new(changes, &PendingPrices::default(), None).unwrap();
+    assert_eq!((diff.new, diff.unchanged, diff.conflicting), (2, 0, 0));
+    assert_eq!(diff.to_string(), "\
++ 2026-10-18 09:00 UTC MyESSO / MyESSO street 1, Berlin: e5 1.759 EUR
++ 2026-10-18 09:00 UTC MyJET / MyJET street 1, Berlin: e5 1.769 EUR
+new: 2, unchanged: 0, conflicting: 0
+");
+}
+
+#[test]
+fn diffs_compare_with_the_price_changes_of_the_same_dry_run() {
+    let pending = PendingPrices::default();
+    // the same document imported twice in one batch
+    let document = [price_change("MyESSO", FuelType::E5, 0, 1759), price_change("MyJET", FuelType::E5, 0, 1769)];
+    let diff = PriceDiff::new(document.iter().chain(document.iter()).cloned(), &pending, None).unwrap();
+    assert_eq!((diff.new, diff.unchanged, diff.conflicting), (2, 2, 0));
+
+    // and in later batches
+    let changes = [
+        price_change("MyESSO", FuelType::E5, 10, 1759),
+        price_change("MyESSO", FuelType::E5, 20, 1749),
+        price_change("MyESSO", FuelType::E5, 5, 1759),
+        price_change("MyESSO", FuelType::E5, 20, 1769),
+        price_change("MyJET", FuelType::E5, 0, 1769),
+    ];
+    let diff = PriceDiff::new(changes, &pending, None).unwrap();
+    assert_eq!((diff.new, diff.unchanged, diff.conflicting), (1, 3, 1));
+    assert_eq!(diff.to_string(), "\
++ 2026-10-18 09:20 UTC MyESSO / MyESSO street 1, Berlin: e5 1.749 EUR
+! 2026-10-18 09:20 UTC MyESSO / MyESSO street 1, Berlin: e5 1.769 EUR, saved 1.749 EUR
+new: 1, unchanged: 3, conflicting: 1
+");
+    let diff = PriceDiff::new([price_change("MyESSO", FuelType::E5, 0, 1759)], &PendingPrices::default(), None).unwrap();
+    assert_eq!(diff.new, 1);
+}
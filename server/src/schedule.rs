@@ -0,0 +1,178 @@
+use chrono::{DateTime, Duration as ChronoDuration, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use rand::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Default maximum deviation from the interval
+pub(crate) const DEFAULT_JITTER: Duration = Duration::from_secs(10 * 60);
+/// Default lower bound of the time between two scrapes
+pub(crate) const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Distribution of the random deviation from the interval
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum JitterDistribution {
+    /// No deviation at all
+    None,
+    /// Every deviation within the jitter range is equally likely
+    #[default]
+    Uniform,
+    /// Small deviations are more likely than large ones
+    Triangular,
+}
+
+/// Daily time range, may wrap around midnight like `22:00-05:00`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TimeWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+/// Time range with a different interval, e.g. to scrape more often during rush hours
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct IntervalOverride {
+    pub hours: TimeWindow,
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+}
+
+/// When to scrape a target again
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Schedule {
+    /// Maximum deviation from the interval
+    #[serde(with = "humantime_serde")]
+    pub jitter: Duration,
+    pub distribution: JitterDistribution,
+    /// Lower bound of the time between two scrapes
+    #[serde(with = "humantime_serde")]
+    pub min: Duration,
+    /// Upper bound of the time between two scrapes
+    #[serde(with = "humantime_serde")]
+    pub max: Option<Duration>,
+    /// Time ranges without any scrapes, e.g. at night, in the time zone of each target
+    pub quiet_hours: Vec<TimeWindow>,
+    /// Time ranges using another interval than the one of the target, in its time zone
+    pub rush_hours: Vec<IntervalOverride>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            jitter: DEFAULT_JITTER,
+            distribution: JitterDistribution::default(),
+            min: DEFAULT_MIN_INTERVAL,
+            max: None,
+            quiet_hours: Vec::new(),
+            rush_hours: Vec::new(),
+        }
+    }
+}
+
+impl Schedule {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.min.is_zero() {
+            return Err("minimal interval must not be zero".to_owned());
+        }
+        if self.max.is_some_and(|max| max < self.min) {
+            return Err("maximal interval must not be less than the minimal interval".to_owned());
+        }
+        if let Some(rush) = self.rush_hours.iter().find(|rush| rush.interval.is_zero()) {
+            return Err(format!("interval of rush hours {} must not be zero", rush.hours));
+        }
+        Ok(())
+    }
+
+    /// End of the quiet hours if `now`, in the time zone of the target, is within quiet hours
+    pub(crate) fn quiet_until(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.quiet_hours.iter()
+            .filter(|quiet| quiet.contains(now.time()))
+            .map(|quiet| quiet.end_after(now))
+            .max()
+    }
+
+    /// Time to sleep from `now`, in the time zone of the target, until its next scrape with the given interval
+    pub(crate) fn next_sleep<R: Rng>(&self, rng: &mut R, interval: Duration, now: DateTime<Tz>) -> Duration {
+        let interval = self.rush_hours.iter()
+            .find(|rush| rush.hours.contains(now.time()))
+            .map_or(interval, |rush| rush.interval);
+
+        let jitter = self.jitter.as_secs_f64();
+        let deviation = match self.distribution {
+            JitterDistribution::None => 0.0,
+            JitterDistribution::Uniform => rng.gen_range(-jitter..=jitter),
+            JitterDistribution::Triangular => (rng.gen_range(-jitter..=jitter) + rng.gen_range(-jitter..=jitter)) / 2.0,
+        };
+        let sleep = if deviation < 0.0 {
+            interval.saturating_sub(Duration::from_secs_f64(-deviation))
+        } else {
+            interval.saturating_add(Duration::from_secs_f64(deviation))
+        };
+        let sleep = sleep.max(self.min);
+        let sleep = self.max.map_or(sleep, |max| sleep.min(max));
+
+        let next = ChronoDuration::from_std(sleep).ok().and_then(|sleep| now.checked_add_signed(sleep));
+        match next.and_then(|next| self.quiet_until(next)) {
+            Some(until) => (until - now).to_std().unwrap_or(sleep),
+            None => sleep,
+        }
+    }
+}
+
+impl TimeWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    /// First end of the window after `now`
+    fn end_after(&self, now: DateTime<Tz>) -> DateTime<Tz> {
+        let mut date = now.date_naive();
+        if self.end <= now.time() {
+            date = date.succ_opt().unwrap_or(date);
+        }
+        let end = date.and_time(self.end);
+        // the end might not exist or be ambiguous because of a DST change
+        now.timezone().from_local_datetime(&end).earliest()
+            .unwrap_or_else(|| now + ChronoDuration::hours(1))
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-').ok_or_else(|| format!("time range expected like 22:00-05:00: {s}"))?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").map_err(|err| format!("invalid start time {start}: {err}"))?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").map_err(|err| format!("invalid end time {end}: {err}"))?;
+        if start == end {
+            return Err(format!("empty time range: {s}"));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeWindow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let window = String::deserialize(deserializer)?;
+        window.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,143 @@
+use super::*;
+use chrono_tz::Europe::{Berlin, Lisbon};
+use rand::rngs::StdRng;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+fn window(s: &str) -> TimeWindow {
+    s.parse().unwrap()
+}
+
+fn local(day: u32, hour: u32, min: u32) -> DateTime<Tz> {
+    Berlin.with_ymd_and_hms(2026, 10, day, hour, min, 0).unwrap()
+}
+
+/// Schedule without any deviation from the interval
+fn exact() -> Schedule {
+    Schedule { distribution: JitterDistribution::None, ..Schedule::default() }
+}
+
+fn samples(schedule: &Schedule, interval: Duration) -> Vec<f64> {
+    let mut rng = StdRng::seed_from_u64(18);
+    (0..10_000).map(|_| schedule.next_sleep(&mut rng, interval, local(18, 12, 0)).as_secs_f64()).collect()
+}
+
+#[test]
+fn time_windows_are_parsed() {
+    assert_eq!(window(" 22:00 - 05:30 ").to_string(), "22:00-05:30");
+    assert!("22:00".parse::<TimeWindow>().is_err());
+    assert!("22:00-25:00".parse::<TimeWindow>().is_err());
+    assert!("05:00-05:00".parse::<TimeWindow>().is_err());
+}
+
+#[test]
+fn time_windows_may_wrap_past_midnight() {
+    let night = window("22:00-05:00");
+    let time = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+    assert!(night.contains(time(22, 0)));
+    assert!(night.contains(time(0, 0)));
+    assert!(night.contains(time(4, 59)));
+    assert!(!night.contains(time(5, 0)));
+    assert!(!night.contains(time(12, 0)));
+
+    let noon = window("12:00-13:00");
+    assert!(noon.contains(time(12, 30)));
+    assert!(!noon.contains(time(13, 0)));
+    assert!(!noon.contains(time(0, 0)));
+}
+
+#[test]
+fn quiet_hours_end_on_the_next_day_past_midnight() {
+    let schedule = Schedule { quiet_hours: vec![window("22:00-05:00")], ..exact() };
+    assert_eq!(schedule.quiet_until(local(18, 23, 30)), Some(local(19, 5, 0)));
+    assert_eq!(schedule.quiet_until(local(19, 2, 0)), Some(local(19, 5, 0)));
+    assert_eq!(schedule.quiet_until(local(19, 5, 0)), None);
+    assert_eq!(schedule.quiet_until(local(18, 12, 0)), None);
+}
+
+#[test]
+fn overlapping_quiet_hours_end_with_the_latest() {
+    let schedule = Schedule { quiet_hours: vec![window("22:00-05:00"), window("04:00-06:00")], ..exact() };
+    assert_eq!(schedule.quiet_until(local(19, 4, 30)), Some(local(19, 6, 0)));
+}
+
+#[test]
+fn quiet_hours_are_in_the_time_zone_of_the_target() {
+    let schedule = Schedule { quiet_hours: vec![window("22:00-05:00")], ..exact() };
+    let now = local(18, 22, 30);
+    assert_eq!(schedule.quiet_until(now), Some(local(19, 5, 0)));
+    // 21:30 in Lisbon
+    assert_eq!(schedule.quiet_until(now.with_timezone(&Lisbon)), None);
+}
+
+#[test]
+fn quiet_hours_ending_within_a_dst_gap_end_an_hour_later() {
+    let schedule = Schedule { quiet_hours: vec![window("01:00-02:30")], ..exact() };
+    // clocks go forward from 02:00 to 03:00
+    let now = Berlin.with_ymd_and_hms(2026, 3, 29, 1, 30, 0).unwrap();
+    assert_eq!(schedule.quiet_until(now), Some(now + ChronoDuration::hours(1)));
+}
+
+#[test]
+fn sleeps_are_clamped_to_the_bounds() {
+    let schedule = Schedule { min: MINUTE, max: Some(60 * MINUTE), ..exact() };
+    let mut rng = StdRng::seed_from_u64(18);
+    let now = local(18, 12, 0);
+    assert_eq!(schedule.next_sleep(&mut rng, 20 * MINUTE, now), 20 * MINUTE);
+    assert_eq!(schedule.next_sleep(&mut rng, Duration::from_secs(10), now), MINUTE);
+    assert_eq!(schedule.next_sleep(&mut rng, 120 * MINUTE, now), 60 * MINUTE);
+
+    // a jitter larger than the interval never sleeps less than the minimum
+    let jittery = Schedule { jitter: 30 * MINUTE, distribution: JitterDistribution::Uniform, ..schedule };
+    let sleeps = samples(&jittery, 20 * MINUTE);
+    assert!(sleeps.iter().all(|sleep| (60.0..=3000.0).contains(sleep)));
+    assert!(sleeps.contains(&60.0));
+}
+
+#[test]
+fn uniform_jitter_spreads_evenly() {
+    let schedule = Schedule { jitter: 10 * MINUTE, distribution: JitterDistribution::Uniform, ..Schedule::default() };
+    let sleeps = samples(&schedule, 20 * MINUTE);
+    assert!(sleeps.iter().all(|sleep| (600.0..=1800.0).contains(sleep)));
+    let central = sleeps.iter().filter(|sleep| (900.0..=1500.0).contains(*sleep)).count() as f64 / sleeps.len() as f64;
+    assert!((0.45..0.55).contains(&central), "{central}");
+}
+
+#[test]
+fn triangular_jitter_prefers_small_deviations() {
+    let schedule = Schedule { jitter: 10 * MINUTE, distribution: JitterDistribution::Triangular, ..Schedule::default() };
+    let sleeps = samples(&schedule, 20 * MINUTE);
+    assert!(sleeps.iter().all(|sleep| (600.0..=1800.0).contains(sleep)));
+    let central = sleeps.iter().filter(|sleep| (900.0..=1500.0).contains(*sleep)).count() as f64 / sleeps.len() as f64;
+    assert!((0.70..0.80).contains(&central), "{central}");
+}
+
+#[test]
+fn rush_hours_override_the_interval() {
+    let schedule = Schedule {
+        rush_hours: vec![IntervalOverride { hours: window("06:00-09:00"), interval: 5 * MINUTE }],
+        ..exact()
+    };
+    let mut rng = StdRng::seed_from_u64(18);
+    assert_eq!(schedule.next_sleep(&mut rng, 20 * MINUTE, local(19, 7, 0)), 5 * MINUTE);
+    assert_eq!(schedule.next_sleep(&mut rng, 20 * MINUTE, local(19, 9, 0)), 20 * MINUTE);
+    // 06:30 in Berlin
+    assert_eq!(schedule.next_sleep(&mut rng, 20 * MINUTE, local(19, 6, 30).with_timezone(&Lisbon)), 20 * MINUTE);
+}
+
+#[test]
+fn sleeps_into_quiet_hours_last_until_their_end() {
+    let schedule = Schedule { quiet_hours: vec![window("23:00-05:00")], ..exact() };
+    let mut rng = StdRng::seed_from_u64(18);
+    assert_eq!(schedule.next_sleep(&mut rng, 20 * MINUTE, local(18, 22, 30)), 20 * MINUTE);
+    assert_eq!(schedule.next_sleep(&mut rng, 20 * MINUTE, local(18, 22, 50)), 370 * MINUTE);
+}
+
+#[test]
+fn invalid_bounds_are_rejected() {
+    assert!(Schedule::default().validate().is_ok());
+    assert!(Schedule { min: Duration::ZERO, ..Schedule::default() }.validate().is_err());
+    assert!(Schedule { max: Some(Duration::from_secs(30)), ..Schedule::default() }.validate().is_err());
+    let rush_hours = vec![IntervalOverride { hours: window("06:00-09:00"), interval: Duration::ZERO }];
+    assert!(Schedule { rush_hours, ..Schedule::default() }.validate().is_err());
+}
@@ -1,10 +1,40 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
-    price_changes (name, addr, updated) {
+    observations (station_id, fuel, observed) {
+        station_id -> Integer,
+        fuel -> Text,
+        observed -> Timestamp,
+        updated -> Timestamp,
+        price -> BigInt,
+        currency -> Text,
+    }
+}
+
+diesel::table! {
+    price_changes (station_id, fuel, updated) {
+        station_id -> Integer,
+        fuel -> Text,
+        updated -> Timestamp,
+        price -> BigInt,
+        currency -> Text,
+    }
+}
+
+diesel::table! {
+    stations (id) {
+        id -> Integer,
         name -> Text,
         addr -> Text,
-        updated -> Timestamp,
-        price -> Integer,
+        merged_into -> Nullable<Integer>,
     }
 }
+
+diesel::joinable!(observations -> stations (station_id));
+diesel::joinable!(price_changes -> stations (station_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    observations,
+    price_changes,
+    stations,
+);
@@ -0,0 +1,72 @@
+mod tankerkoenig;
+
+pub(crate) use self::tankerkoenig::TankerkoenigSource;
+
+use crate::error::ParseError;
+use crate::fuel::FuelType;
+use crate::models::RefuelStationPriceChange;
+use crate::parse::PriceListSource;
+use crate::profile::SiteProfile;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// A site layout or API which lists refuel station prices
+#[async_trait(?Send)]
+pub(crate) trait PriceSource {
+    /// Turns a document downloaded at `scraped` into the price changes it lists,
+    /// fails only if the document as a whole is not understood
+    async fn parse(&self, document: &str, fuel: FuelType, scraped: DateTime<Utc>) -> Result<ParsedDocument, ParseError>;
+}
+
+/// Price changes parsed from a document and the items skipped
+#[derive(Default)]
+pub(crate) struct ParsedDocument {
+    pub stations: VecDeque<RefuelStationPriceChange>,
+    pub skipped: Vec<ItemError>,
+}
+
+/// Why an item of a document was skipped
+#[derive(Debug)]
+pub(crate) struct ItemError {
+    /// Position of the item within the document
+    pub item: usize,
+    pub error: ParseError,
+    /// Html or json of the item
+    pub sample: String,
+}
+
+/// The price sources selectable per target
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum SourceKind {
+    /// Html price list using the `.PriceList__*` classes
+    #[default]
+    PriceList,
+    /// Json station list like the one of the Tankerkönig API
+    Tankerkoenig,
+}
+
+impl SourceKind {
+    /// The parser of this kind, html price lists are parsed using `profile`
+    /// and show their times in `timezone`
+    pub(crate) fn source(&self, profile: &Arc<SiteProfile>, timezone: Tz) -> Box<dyn PriceSource> {
+        match self {
+            SourceKind::PriceList => Box::new(PriceListSource { profile: profile.clone(), timezone }),
+            SourceKind::Tankerkoenig => Box::new(TankerkoenigSource),
+        }
+    }
+
+    /// Name of the url query parameter selecting the fuel type
+    pub(crate) fn fuel_param(&self) -> &'static str {
+        match self {
+            SourceKind::PriceList => "fuel",
+            SourceKind::Tankerkoenig => "type",
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use super::{ItemError, ParsedDocument, PriceSource};
+use crate::error::ParseError;
+use crate::fuel::FuelType;
+use crate::models::RefuelStationPriceChange;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+
+/// Json station list as returned by the `list.php` endpoint of the Tankerkönig API.
+/// The API does not tell when a price changed, so the time of scraping is used.
+pub(crate) struct TankerkoenigSource;
+
+#[derive(Deserialize)]
+struct StationList {
+    ok: bool,
+    #[serde(default)]
+    message: Option<String>,
+    /// kept as json, so a single odd station only skips itself
+    #[serde(default)]
+    stations: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Station {
+    name: String,
+    street: String,
+    #[serde(default)]
+    house_number: Option<String>,
+    post_code: Value,
+    place: String,
+    /// price of the requested fuel type if only one was requested,
+    /// prices are `null` or `false` if the station is closed
+    #[serde(default)]
+    price: Value,
+    #[serde(default)]
+    diesel: Value,
+    #[serde(default)]
+    e5: Value,
+    #[serde(default)]
+    e10: Value,
+}
+
+impl Station {
+    fn addr(&self) -> String {
+        let post_code = match &self.post_code {
+            Value::Number(post_code) => post_code.as_u64().map_or_else(|| post_code.to_string(), |code| format!("{code:05}")),
+            Value::String(post_code) => post_code.clone(),
+            _ => String::new(),
+        };
+        let street = match self.house_number.as_deref().map(str::trim) {
+            Some(house_number) if !house_number.is_empty() => format!("{} {}", self.street.trim(), house_number),
+            _ => self.street.trim().to_owned(),
+        };
+        format!("{street}, {post_code} {}", self.place.trim())
+    }
+
+    fn price(&self, fuel: FuelType) -> Option<f64> {
+        let price = match fuel {
+            FuelType::Diesel => &self.diesel,
+            FuelType::E5 => &self.e5,
+            FuelType::E10 => &self.e10,
+            // not listed by the api
+            FuelType::Lpg | FuelType::Cng => return None,
+        };
+        price.as_f64().or_else(|| self.price.as_f64())
+    }
+}
+
+#[async_trait(?Send)]
+impl PriceSource for TankerkoenigSource {
+    #[tracing::instrument(skip(self, document))]
+    async fn parse(&self, document: &str, fuel: FuelType, scraped: DateTime<Utc>) -> Result<ParsedDocument, ParseError> {
+        let list: StationList = serde_json::from_str(document)?;
+        if !list.ok {
+            return Err(ParseError::ApiError(list.message.unwrap_or_default()));
+        }
+
+        let mut parsed = ParsedDocument::default();
+        for (item, station) in list.stations.into_iter().enumerate() {
+            let sample = station.to_string();
+            let station: Station = match serde_json::from_value(station) {
+                Ok(station) => station,
+                Err(err) => {
+                    parsed.skipped.push(ItemError { item, error: err.into(), sample });
+                    continue;
+                }
+            };
+            let price = match station.price(fuel) {
+                // prices are given in euro with three decimals
+                Some(price) if price > 0.0 && price < 1e12 => (price * 1000.0).round() as i64,
+                _ => {
+                    let error = ParseError::MissingPriceError { name: station.name, fuel };
+                    parsed.skipped.push(ItemError { item, error, sample });
+                    continue;
+                }
+            };
+            let addr = station.addr();
+            let name = station.name.trim().to_owned();
+            parsed.stations.push_back(RefuelStationPriceChange { name, addr, fuel, updated: scraped, observed: scraped, price, currency: "EUR".to_owned() });
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,77 @@
+use super::*;
+use crate::report::ParseReport;
+use chrono::TimeZone;
+
+const LIST: &str = include_str!("../../../tests/fragments/tankerkoenig.json");
+
+fn scraped() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 18, 9, 0, 0).unwrap()
+}
+
+async fn parse(document: &str, fuel: FuelType) -> Result<ParsedDocument, ParseError> {
+    TankerkoenigSource.parse(document, fuel, scraped()).await
+}
+
+fn stations(parsed: &ParsedDocument) -> Vec<(&str, &str, i64)> {
+    parsed.stations.iter().map(|rs| (rs.name.as_str(), rs.addr.as_str(), rs.price)).collect()
+}
+
+#[tokio::test]
+async fn stations_are_parsed_with_the_price_of_the_fuel() {
+    let parsed = parse(LIST, FuelType::Diesel).await.unwrap();
+    assert_eq!(stations(&parsed), vec![
+        ("MyTOTAL BERLIN", "MARGARETE-SOMMER-STR. 2, 10407 BERLIN", 1659),
+        ("MyARAL Tankstelle", "Holzmarktstraße 12, 01067 Berlin", 1649),
+        // numeric post codes lose their leading zero
+        ("MyJET BERLIN", "Landsberger Allee 376, 01067 BERLIN", 1639),
+    ]);
+    // the api doesn't tell when a price changed
+    assert!(parsed.stations.iter().all(|rs| rs.updated == scraped() && rs.observed == scraped() && rs.currency == "EUR"));
+
+    let e5 = parse(LIST, FuelType::E5).await.unwrap();
+    assert_eq!(e5.stations.iter().map(|rs| rs.price).collect::<Vec<_>>(), vec![1759, 1749]);
+    assert!(parse(LIST, FuelType::Lpg).await.unwrap().stations.is_empty());
+}
+
+#[tokio::test]
+async fn single_fuel_lists_give_one_price() {
+    let list = r#"{"ok": true, "stations": [{"name": "MyHEM", "street": "Frankfurter Allee", "houseNumber": "1", "postCode": 10247, "place": "Berlin", "price": 1.729}]}"#;
+    let parsed = parse(list, FuelType::E10).await.unwrap();
+    assert_eq!(stations(&parsed), vec![("MyHEM", "Frankfurter Allee 1, 10247 Berlin", 1729)]);
+}
+
+#[tokio::test]
+async fn closed_stations_are_unavailable() {
+    let parsed = parse(LIST, FuelType::E10).await.unwrap();
+    let skipped: Vec<_> = parsed.skipped.iter().map(|skipped| (skipped.item, skipped.error.reason())).collect();
+    assert_eq!(skipped, vec![(1, "missing-price"), (3, "json")]);
+    assert!(parsed.skipped[0].error.is_unavailable());
+    assert!(parsed.skipped[0].sample.contains("\"name\":\"MyARAL Tankstelle\""));
+    // false as well as null
+    let e5 = parse(LIST, FuelType::E5).await.unwrap();
+    assert!(matches!(&e5.skipped[0].error, ParseError::MissingPriceError { name, fuel: FuelType::E5 } if name == "MyARAL Tankstelle"));
+}
+
+#[tokio::test]
+async fn malformed_stations_are_skipped_and_reported() {
+    let parsed = parse(LIST, FuelType::Diesel).await.unwrap();
+    assert_eq!(parsed.stations.len(), 3);
+    assert_eq!(parsed.skipped.len(), 1);
+    assert!(matches!(parsed.skipped[0].error, ParseError::JsonError(_)));
+    assert!(!parsed.skipped[0].error.is_unavailable());
+    assert!(parsed.skipped[0].sample.contains("\"brand\":\"MyESSO\""));
+
+    // the closed station doesn't count as skipped, the malformed one does
+    let report = ParseReport::new(&parse(LIST, FuelType::E10).await.unwrap());
+    assert_eq!((report.items(), report.parsed), (4, 2));
+    assert_eq!(report.skip_ratio(), 0.25);
+}
+
+#[tokio::test]
+async fn api_errors_fail_the_whole_document() {
+    let err = parse(include_str!("../../../tests/fragments/tankerkoenig_error.json"), FuelType::E5).await.err().unwrap();
+    assert!(matches!(err, ParseError::ApiError(message) if message.starts_with("apikey nicht angegeben")));
+    assert!(matches!(parse(r#"{"ok": false}"#, FuelType::E5).await, Err(ParseError::ApiError(message)) if message.is_empty()));
+    assert!(matches!(parse("<html></html>", FuelType::E5).await, Err(ParseError::JsonError(_))));
+    assert!(matches!(parse(r#"{"stations": []}"#, FuelType::E5).await, Err(ParseError::JsonError(_))));
+}
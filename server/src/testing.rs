@@ -0,0 +1,31 @@
+//! Fixtures shared by the unit tests of several modules
+
+use crate::fuel::FuelType;
+use crate::models::RefuelStationPriceChange;
+
+use chrono::{TimeZone, Utc};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Empty directory of the system's temporary directory, unique to this test process
+pub(crate) fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("refuel-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Price change of a station in Berlin updated and scraped at `minute` past nine on 2026-10-18
+pub(crate) fn price_change(name: &str, fuel: FuelType, minute: u32, price: i64) -> RefuelStationPriceChange {
+    let updated = Utc.with_ymd_and_hms(2026, 10, 18, 9, minute, 0).unwrap();
+    RefuelStationPriceChange {
+        name: name.to_owned(),
+        addr: format!("{name} street 1, Berlin"),
+        fuel,
+        updated,
+        observed: updated,
+        price,
+        currency: "EUR".to_owned(),
+    }
+}
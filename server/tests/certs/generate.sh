@@ -0,0 +1,19 @@
+#!/bin/sh
+# Regenerates the certificates of the download tests: a root CA standing in for the one
+# of a tls inspecting proxy, a server certificate for refuel.test and a client certificate
+set -e
+cd "$(dirname "$0")"
+
+openssl req -x509 -newkey rsa:2048 -nodes -days 36500 -subj "/CN=Refuel Test CA" \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign,cRLSign" \
+    -keyout ca.key -out ca.pem
+
+openssl req -newkey rsa:2048 -nodes -subj "/CN=refuel.test" -keyout server.key -out server.csr
+printf "subjectAltName=DNS:refuel.test\nextendedKeyUsage=serverAuth\n" > server.ext
+openssl x509 -req -days 36500 -in server.csr -CA ca.pem -CAkey ca.key -CAcreateserial -extfile server.ext -out server.pem
+
+openssl req -newkey rsa:2048 -nodes -subj "/CN=refuel-server" -keyout client.key -out client.csr
+printf "extendedKeyUsage=clientAuth\n" > client.ext
+openssl x509 -req -days 36500 -in client.csr -CA ca.pem -CAkey ca.key -CAcreateserial -extfile client.ext -out client.pem
+
+rm -f ca.key ca.srl server.csr server.ext client.csr client.ext
@@ -3,12 +3,13 @@ use std::sync::Arc;
 
 use askama::Template;
 use askama_axum::{IntoResponse, Response};
-use axum::{extract::State, routing::get, Router};
+use axum::{extract::{Query, State}, routing::get, Json, Router};
 use axum_macros::debug_handler;
 use tokio::sync::RwLock;
 use tokio::time::{sleep, Duration};
 use tokio::try_join;
 use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
 
 use tracing_subscriber::EnvFilter;
 
@@ -31,6 +32,18 @@ impl RefuelStationData {
         Self { name, addr, updated, price }
     }
 
+    /// Price of the given fuel type, the base price is the Super E5 price
+    fn with_fuel(&self, fuel: Option<&str>) -> Self {
+        let discount = match fuel {
+            Some("diesel") => 8,
+            Some("e10") => 6,
+            _ => 0,
+        };
+        let mut data = self.clone();
+        data.price[1] -= discount;
+        data
+    }
+
     fn update(&mut self, price: [u8; 3]) {
         let updated = Local::now();
         self.price = price;
@@ -64,13 +77,70 @@ impl Default for AppState {
     }
 }
 
+#[derive(Deserialize)]
+struct HomeQuery {
+    fuel: Option<String>,
+}
+
 #[debug_handler]
-async fn home(State(state): State<Arc<RwLock<AppState>>>) -> Response {
+async fn home(State(state): State<Arc<RwLock<AppState>>>, Query(query): Query<HomeQuery>) -> Response {
     let state = state.read().await;
-    let data = &state.data;
+    let data: Vec<RefuelStationData> = state.data.iter()
+        .map(|data| data.with_fuel(query.fuel.as_deref()))
+        .collect();
     HomeTemplate { price_list: &data }.into_response()
 }
 
+#[derive(Deserialize)]
+struct ListQuery {
+    #[serde(rename = "type")]
+    fuel: Option<String>,
+}
+
+#[derive(Serialize)]
+struct StationList {
+    ok: bool,
+    stations: Vec<Station>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Station {
+    name: String,
+    street: String,
+    house_number: String,
+    post_code: u32,
+    place: String,
+    price: f64,
+}
+
+impl From<RefuelStationData> for Station {
+    fn from(src: RefuelStationData) -> Self {
+        // "Rhinstr. 240, 13055 Berlin"
+        let (street, place) = src.addr.split_once(", ").unwrap_or((&src.addr, ""));
+        let (street, house_number) = street.rsplit_once(' ').unwrap_or((street, ""));
+        let (post_code, place) = place.split_once(' ').unwrap_or(("0", place));
+        let price = f64::from(src.price[0]) + f64::from(src.price[1]) / 100.0 + f64::from(src.price[2]) / 1000.0;
+        Self {
+            name: src.name,
+            street: street.to_owned(),
+            house_number: house_number.to_owned(),
+            post_code: post_code.parse().unwrap_or_default(),
+            place: place.to_owned(),
+            price,
+        }
+    }
+}
+
+/// Station list like the one of the Tankerkönig API
+async fn list(State(state): State<Arc<RwLock<AppState>>>, Query(query): Query<ListQuery>) -> Json<StationList> {
+    let state = state.read().await;
+    let stations = state.data.iter()
+        .map(|data| data.with_fuel(query.fuel.as_deref()).into())
+        .collect();
+    Json(StationList { ok: true, stations })
+}
+
 async fn change_state(state: Arc<RwLock<AppState>>) -> Result<(), hyper::Error> {
     let mut i = 0;
     loop {
@@ -97,6 +167,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let app = Router::new()
         .route("/", get(home))
+        .route("/json/list.php", get(list))
         .with_state(Arc::clone(&state));
 
     let addr: SocketAddr = "127.0.0.1:8080".parse().expect("invalid socket address");